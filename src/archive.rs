@@ -0,0 +1,207 @@
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use chrono::{Local, NaiveDate, TimeZone};
+
+/// An entry stored in an archive.
+#[derive(Debug)]
+pub struct Entry {
+    /// Path of the entry inside the archive, without a leading `./`.
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// Lists the entries of a tar archive, which may be zstd-compressed, or a zip archive, without
+/// extracting them.
+///
+/// Directories that only appear as parents of other entries are listed too.
+pub fn list(path: &Path) -> Result<Vec<Entry>, String> {
+    let err = |err: std::io::Error| format!("cannot read archive '{}': {}", path.display(), err);
+    let mut file = File::open(path).map_err(err)?;
+    let mut magic = [0u8; 4];
+    let len = file.read(&mut magic).map_err(err)?;
+    file.rewind().map_err(err)?;
+    let mut entries = match &magic[..len] {
+        [b'P', b'K', 3, 4] | [b'P', b'K', 5, 6] => read_zip(file).map_err(err)?,
+        [0x28, 0xb5, 0x2f, 0xfd] => read_tar(zstd::Decoder::new(file).map_err(err)?).map_err(err)?,
+        [0x1f, 0x8b, ..] => return Err(format!("cannot read archive '{}': gzip compression is not supported", path.display())),
+        _ => read_tar(BufReader::new(file)).map_err(err)?,
+    };
+    let listed: BTreeSet<PathBuf> = entries.iter().filter(|e| e.is_dir).map(|e| e.path.clone()).collect();
+    let parents: BTreeSet<PathBuf> = entries
+        .iter()
+        .flat_map(|e| e.path.ancestors().skip(1))
+        .filter(|p| !p.as_os_str().is_empty() && !listed.contains(*p))
+        .map(ToOwned::to_owned)
+        .collect();
+    entries.extend(parents.into_iter().map(|path| Entry { path, is_dir: true, size: 0, modified: None }));
+    Ok(entries)
+}
+
+/// Path of an entry relative to the root of the archive, or `None` for the root itself.
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let path: PathBuf = path.components().filter(|c| matches!(c, Component::Normal(_))).collect();
+    (!path.as_os_str().is_empty()).then_some(path)
+}
+
+fn read_tar(reader: impl Read) -> std::io::Result<Vec<Entry>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let header = entry.header();
+        let Some(path) = normalize(&entry.path()?) else {
+            continue;
+        };
+        let modified = header.mtime().ok().map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+        entries.push(Entry { path, is_dir: header.entry_type().is_dir(), size: header.size()?, modified });
+    }
+    Ok(entries)
+}
+
+/// Signature of the record at the end of a zip archive locating its central directory.
+const ZIP_END: [u8; 4] = *b"PK\x05\x06";
+/// Signature of each file header in the central directory of a zip archive.
+const ZIP_HEADER: [u8; 4] = *b"PK\x01\x02";
+
+/// Reads the entries of a zip archive from its central directory.
+fn read_zip(mut file: impl Read + Seek) -> std::io::Result<Vec<Entry>> {
+    let invalid = |msg: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, msg.to_string());
+    // The end record is 22 bytes followed by a comment of at most 64 KiB.
+    let len = file.seek(SeekFrom::End(0))?;
+    let tail_len = len.min(22 + u16::MAX as u64);
+    file.seek(SeekFrom::Start(len - tail_len))?;
+    let mut tail = vec![0u8; tail_len as usize];
+    file.read_exact(&mut tail)?;
+    let end = tail.windows(4).rposition(|w| w == ZIP_END).filter(|&i| tail.len() - i >= 22).ok_or_else(|| invalid("missing end of central directory"))?;
+    let end = &tail[end..];
+    let u16_at = |buf: &[u8], i: usize| u16::from_le_bytes([buf[i], buf[i + 1]]);
+    let u32_at = |buf: &[u8], i: usize| u32::from_le_bytes([buf[i], buf[i + 1], buf[i + 2], buf[i + 3]]);
+    let (count, dir_size, dir_offset) = (u16_at(end, 10), u32_at(end, 12), u32_at(end, 16));
+    if count == u16::MAX || dir_offset == u32::MAX {
+        return Err(invalid("zip64 archives are not supported"));
+    }
+    file.seek(SeekFrom::Start(dir_offset as u64))?;
+    let mut dir = vec![0u8; dir_size as usize];
+    file.read_exact(&mut dir)?;
+
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    for _ in 0..count {
+        let header = dir.get(pos..pos + 46).filter(|h| h[..4] == ZIP_HEADER).ok_or_else(|| invalid("corrupt central directory"))?;
+        let (time, date, size) = (u16_at(header, 12), u16_at(header, 14), u32_at(header, 24));
+        let (name_len, extra_len, comment_len) = (u16_at(header, 28) as usize, u16_at(header, 30) as usize, u16_at(header, 32) as usize);
+        let name = dir.get(pos + 46..pos + 46 + name_len).ok_or_else(|| invalid("corrupt central directory"))?;
+        pos += 46 + name_len + extra_len + comment_len;
+        // Names without the UTF-8 flag are usually in the encoding of the system that made the
+        // archive, so their bytes are kept as they are, like those of tar archives.
+        let is_dir = name.ends_with(b"/");
+        let Some(path) = normalize(Path::new(&lsn::os_string_from_bytes(name))) else {
+            continue;
+        };
+        entries.push(Entry { path, is_dir, size: size as u64, modified: dos_time(date, time) });
+    }
+    Ok(entries)
+}
+
+/// Converts an MS-DOS date and time, as stored in zip archives in local time, to a time.
+fn dos_time(date: u16, time: u16) -> Option<SystemTime> {
+    let date = NaiveDate::from_ymd_opt(1980 + (date >> 9) as i32, ((date >> 5) & 0xf) as u32, (date & 0x1f) as u32)?;
+    let time = date.and_hms_opt((time >> 11) as u32, ((time >> 5) & 0x3f) as u32, (time & 0x1f) as u32 * 2)?;
+    Local.from_local_datetime(&time).earliest().map(SystemTime::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// A stored zip archive of the given names and contents, with all entries dated
+    /// 2024-03-15 13:45:30.
+    fn zip(files: &[(&[u8], &[u8])]) -> Vec<u8> {
+        let (time, date) = (13 << 11 | 45 << 5 | 15, 44 << 9 | 3 << 5 | 15);
+        let mut out = Vec::new();
+        let mut dir = Vec::new();
+        for &(name, data) in files {
+            let offset = out.len() as u32;
+            // Version needed, flags, method (stored), time and date, then crc and sizes.
+            let fields = [20u16.to_le_bytes(), 0u16.to_le_bytes(), 0u16.to_le_bytes(), (time as u16).to_le_bytes(), (date as u16).to_le_bytes()].concat();
+            let sizes = [0u32.to_le_bytes(), (data.len() as u32).to_le_bytes(), (data.len() as u32).to_le_bytes()].concat();
+            let lens = [(name.len() as u16).to_le_bytes(), 0u16.to_le_bytes()].concat();
+            out.extend([b"PK\x03\x04".as_slice(), &fields, &sizes, &lens, name, data].concat());
+            let trailer = [0u16.to_le_bytes(), 0u16.to_le_bytes(), 0u16.to_le_bytes()].concat();
+            dir.extend([&ZIP_HEADER[..], &20u16.to_le_bytes(), &fields, &sizes, &lens, &trailer, &0u32.to_le_bytes(), &offset.to_le_bytes(), name].concat());
+        }
+        let (offset, count) = (out.len() as u32, files.len() as u16);
+        out.extend_from_slice(&dir);
+        out.extend([&ZIP_END[..], &[0; 4], &count.to_le_bytes(), &count.to_le_bytes(), &(dir.len() as u32).to_le_bytes(), &offset.to_le_bytes(), &[0; 2]].concat());
+        out
+    }
+
+    fn paths(entries: &[Entry]) -> Vec<(&str, bool, u64)> {
+        entries.iter().map(|e| (e.path.to_str().unwrap(), e.is_dir, e.size)).collect()
+    }
+
+    #[test]
+    fn zip_entries() {
+        let archive = zip(&[(b"./", b""), (b"shots/", b""), (b"shots/frame1.exr", b"abc"), (b"shots/frame2.exr", b"de")]);
+        let entries = read_zip(Cursor::new(archive)).unwrap();
+        assert_eq!(vec![("shots", true, 0), ("shots/frame1.exr", false, 3), ("shots/frame2.exr", false, 2)], paths(&entries));
+        assert_eq!(dos_time(44 << 9 | 3 << 5 | 15, 13 << 11 | 45 << 5 | 15), entries[1].modified);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn zip_non_utf8_names() {
+        use std::os::unix::ffi::OsStrExt;
+        let entries = read_zip(Cursor::new(zip(&[(b"caf\xe9.exr", b"")]))).unwrap();
+        assert_eq!(b"caf\xe9.exr", entries[0].path.as_os_str().as_bytes());
+    }
+
+    #[test]
+    fn zip_errors() {
+        let err = read_zip(Cursor::new(b"PK\x03\x04 not really a zip".to_vec())).unwrap_err();
+        assert_eq!("missing end of central directory", err.to_string());
+        // An end record claiming more entries than the central directory holds.
+        let mut archive = zip(&[(b"a.exr", b"")]);
+        let count = archive.len() - 12;
+        archive[count] = 2;
+        assert_eq!("corrupt central directory", read_zip(Cursor::new(archive)).unwrap_err().to_string());
+        let mut archive = zip(&[]);
+        let count = archive.len() - 12;
+        archive[count..count + 2].copy_from_slice(&[0xff, 0xff]);
+        assert_eq!("zip64 archives are not supported", read_zip(Cursor::new(archive)).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn tar_entries() {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_size(0);
+        header.set_mtime(1_700_000_000);
+        builder.append_data(&mut header, "./shots/", std::io::empty()).unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_size(3);
+        header.set_mtime(1_700_000_000);
+        builder.append_data(&mut header, "shots/frame1.exr", b"abc".as_slice()).unwrap();
+        let entries = read_tar(Cursor::new(builder.into_inner().unwrap())).unwrap();
+        assert_eq!(vec![("shots", true, 0), ("shots/frame1.exr", false, 3)], paths(&entries));
+        assert_eq!(Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)), entries[1].modified);
+    }
+
+    #[test]
+    fn dos_times() {
+        let time = dos_time(44 << 9 | 3 << 5 | 15, 13 << 11 | 45 << 5 | 15).unwrap();
+        let expected = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap().and_hms_opt(13, 45, 30).unwrap();
+        assert_eq!(expected, chrono::DateTime::<Local>::from(time).naive_local());
+        // Month 0 and hour 24 are out of range.
+        assert_eq!(None, dos_time(44 << 9 | 15, 0));
+        assert_eq!(None, dos_time(44 << 9 | 3 << 5 | 15, 24 << 11));
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::hash_map::DefaultHasher;
+use std::ffi::OsString;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::colors::Kind;
+
+/// Version of the snapshot format, bumped whenever it changes.
+const VERSION: u32 = 1;
+
+/// Directories modified more recently than this aren't cached, since a change within the
+/// resolution of their modification time could go unnoticed.
+const MIN_AGE: Duration = Duration::from_secs(2);
+
+/// Names and types of the entries of a directory, valid while its modification time is unchanged.
+#[derive(Debug, Serialize, Deserialize)]
+struct Snapshot {
+    version: u32,
+    /// Canonical path of the directory as encoded bytes, guarding against hash collisions.
+    dir: Vec<u8>,
+    modified: SystemTime,
+    /// Whether entry types are those of symlink targets.
+    follow_links: bool,
+    entries: Vec<(Vec<u8>, Kind)>,
+}
+
+/// Directory holding snapshots: `lsn` in `XDG_CACHE_HOME`, falling back to `~/.cache`.
+fn cache_dir() -> Option<PathBuf> {
+    let dir = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(dir.join("lsn"))
+}
+
+/// Canonical path of `dir`, its modification time and the file its snapshot is stored in.
+fn locate(dir: &Path) -> Option<(PathBuf, SystemTime, PathBuf)> {
+    let dir = std::fs::canonicalize(dir).ok()?;
+    let meta = std::fs::metadata(&dir).ok().filter(|meta| meta.is_dir())?;
+    let mut hasher = DefaultHasher::new();
+    dir.hash(&mut hasher);
+    let file = cache_dir()?.join(format!("{:016x}.json", hasher.finish()));
+    Some((dir, meta.modified().ok()?, file))
+}
+
+/// Returns the names and types of the entries of `dir` from its snapshot, if the directory hasn't
+/// been modified since the snapshot was stored.
+pub fn load(dir: &Path, follow_links: bool) -> Option<Vec<(OsString, Kind)>> {
+    let (dir, modified, file) = locate(dir)?;
+    let contents = std::fs::read(file).ok()?;
+    let snapshot: Snapshot = serde_json::from_slice(&contents).ok()?;
+    let valid = snapshot.version == VERSION
+        && snapshot.dir == dir.as_os_str().as_encoded_bytes()
+        && snapshot.modified == modified
+        && snapshot.follow_links == follow_links;
+    valid.then(|| snapshot.entries.into_iter().map(|(name, kind)| (lsn::os_string_from_bytes(&name), kind)).collect())
+}
+
+/// Stores a snapshot of the entries of `dir`. Failures are ignored since the cache is only an
+/// optimization.
+pub fn store(dir: &Path, follow_links: bool, entries: &[(OsString, Kind)]) {
+    let Some((dir, modified, file)) = locate(dir) else {
+        return;
+    };
+    if SystemTime::now().duration_since(modified).map_or(true, |age| age < MIN_AGE) {
+        return;
+    }
+    let snapshot = Snapshot {
+        version: VERSION,
+        dir: dir.as_os_str().as_encoded_bytes().to_vec(),
+        modified,
+        follow_links,
+        entries: entries.iter().map(|(name, kind)| (name.as_encoded_bytes().to_vec(), *kind)).collect(),
+    };
+    let Ok(contents) = serde_json::to_vec(&snapshot) else {
+        return;
+    };
+    // Written to a temporary file first so concurrent listings never read a partial snapshot.
+    let tmp = file.with_extension(format!("{}.tmp", std::process::id()));
+    let written = file.parent().is_some_and(|parent| std::fs::create_dir_all(parent).is_ok())
+        && std::fs::write(&tmp, contents).is_ok()
+        && std::fs::rename(&tmp, &file).is_ok();
+    if !written {
+        let _ = std::fs::remove_file(&tmp);
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use lsn::{FileGroup, Meta};
+
+/// Kind of entry used to pick a color.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Kind {
+    File,
+    Dir,
+    Symlink,
+    Executable,
+    Fifo,
+    Socket,
+}
+
+impl Kind {
+    /// Kind of an entry with the given file type. Executables can't be told apart from other
+    /// files by their type alone.
+    pub fn of(file_type: std::fs::FileType) -> Kind {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+            if file_type.is_fifo() {
+                return Kind::Fifo;
+            }
+            if file_type.is_socket() {
+                return Kind::Socket;
+            }
+        }
+        if file_type.is_dir() {
+            Kind::Dir
+        } else if file_type.is_symlink() {
+            Kind::Symlink
+        } else {
+            Kind::File
+        }
+    }
+
+    /// Kind of a group, from its aggregate metadata.
+    pub fn of_group(grp: &FileGroup) -> Kind {
+        match grp.meta.as_ref() {
+            Some(meta) if meta.is_dir => Kind::Dir,
+            Some(meta) if meta.is_symlink => Kind::Symlink,
+            Some(meta) if meta.is_fifo => Kind::Fifo,
+            Some(meta) if meta.is_socket => Kind::Socket,
+            Some(meta) if meta.is_executable => Kind::Executable,
+            _ => Kind::File,
+        }
+    }
+
+    /// Metadata known from a directory listing without a `stat` call, which has only the kind
+    /// of file, with no times and a size of zero.
+    pub fn meta(self) -> Meta {
+        Meta {
+            is_dir: self == Kind::Dir,
+            is_symlink: self == Kind::Symlink,
+            is_executable: self == Kind::Executable,
+            is_fifo: self == Kind::Fifo,
+            is_socket: self == Kind::Socket,
+            ..Meta::default()
+        }
+    }
+
+    /// Key used for this kind in `LS_COLORS`.
+    fn key(&self) -> &'static str {
+        match self {
+            Kind::File => "fi",
+            Kind::Dir => "di",
+            Kind::Symlink => "ln",
+            Kind::Executable => "ex",
+            Kind::Fifo => "pi",
+            Kind::Socket => "so",
+        }
+    }
+}
+
+/// Colors for entries given as SGR sequences in the `LS_COLORS` format, e.g. `di=01;34:*.exr=33`.
+#[derive(Clone, Debug)]
+pub struct Colors {
+    /// Colors by entry kind key (`di`, `ln`, `fi`).
+    kinds: HashMap<String, String>,
+    /// Colors by file name suffix, given as `*.ext` entries.
+    suffixes: Vec<(String, String)>,
+    /// Styles from the config file, added to the colors above.
+    rules: Vec<(Rule, String)>,
+    /// Colors of times younger than each age, youngest first.
+    ages: Vec<(Duration, String)>,
+}
+
+/// Names a style rule from the config file applies to.
+#[derive(Clone, Debug)]
+enum Rule {
+    /// Lowercase extension including the leading `.`, matched case-insensitively.
+    Ext(String),
+    /// Glob matched against the file name.
+    Glob(glob::Pattern),
+}
+
+impl Rule {
+    fn new(key: &str) -> Result<Rule, String> {
+        if key.contains(['*', '?', '[']) {
+            glob::Pattern::new(key).map(Rule::Glob).map_err(|err| format!("invalid color rule '{}': {}", key, err))
+        } else {
+            Ok(Rule::Ext(format!(".{}", key.trim_start_matches('.').to_ascii_lowercase())))
+        }
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            Rule::Ext(ext) => name.to_ascii_lowercase().ends_with(ext.as_str()),
+            Rule::Glob(pattern) => pattern.matches(name),
+        }
+    }
+}
+
+/// Colors from the 256 color palette for sizes from small to large relative to the largest, going
+/// from green through yellow to red.
+const HEAT: [u8; 6] = [28, 34, 106, 178, 208, 196];
+
+/// Wraps `text` in the color for a size that is `fraction` of the largest.
+pub fn paint_heat(fraction: f64, text: &str) -> String {
+    let step = (fraction.clamp(0.0, 1.0) * (HEAT.len() - 1) as f64).round() as usize;
+    format!("\x1b[38;5;{}m{}\x1b[0m", HEAT[step], text)
+}
+
+/// Converts a style such as `bold underline yellow` or an SGR code such as `01;33` to an SGR code.
+pub fn parse_style(style: &str) -> Result<String, String> {
+    if !style.is_empty() && style.bytes().all(|b| b.is_ascii_digit() || b == b';') {
+        return Ok(style.to_string());
+    }
+    const COLORS: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+    let mut codes = Vec::new();
+    for word in style.split(|c: char| c.is_whitespace() || c == ',').filter(|w| !w.is_empty()) {
+        let word = word.to_ascii_lowercase();
+        let code = match word.as_str() {
+            "bold" => 1,
+            "dim" => 2,
+            "italic" => 3,
+            "underline" => 4,
+            "blink" => 5,
+            "reverse" => 7,
+            "strikethrough" => 9,
+            _ => {
+                let (base, color) = if let Some(color) = word.strip_prefix("on-") {
+                    (40, color)
+                } else if let Some(color) = word.strip_prefix("bright-") {
+                    (90, color)
+                } else {
+                    (30, word.as_str())
+                };
+                let index = COLORS.iter().position(|&c| c == color).ok_or_else(|| format!("unknown style '{}'", word))?;
+                base + index
+            }
+        };
+        codes.push(code.to_string());
+    }
+    if codes.is_empty() {
+        return Err(format!("empty style '{}'", style));
+    }
+    Ok(codes.join(";"))
+}
+
+impl Default for Colors {
+    fn default() -> Self {
+        let mut kinds = HashMap::new();
+        kinds.insert("di".to_string(), "34".to_string());
+        kinds.insert("ln".to_string(), "35".to_string());
+        kinds.insert("ex".to_string(), "01;32".to_string());
+        // Within the last hour, day and week.
+        let ages = vec![(Duration::from_secs(3600), "1;32".to_string()), (Duration::from_secs(86400), "32".to_string()), (Duration::from_secs(7 * 86400), "33".to_string())];
+        Colors { kinds, suffixes: Vec::new(), rules: Vec::new(), ages }
+    }
+}
+
+impl Colors {
+    /// Builds colors from the defaults, overridden by `LS_COLORS` and then `LSN_COLORS`.
+    pub fn from_env() -> Self {
+        let mut colors = Colors::default();
+        for var in ["LS_COLORS", "LSN_COLORS"] {
+            if let Ok(spec) = std::env::var(var) {
+                colors.extend(&spec);
+            }
+        }
+        colors
+    }
+
+    /// Adds the entries of an `LS_COLORS` style spec, replacing existing ones.
+    pub fn extend(&mut self, spec: &str) {
+        for entry in spec.split(':') {
+            let Some((key, code)) = entry.split_once('=') else {
+                continue;
+            };
+            if let Some(suffix) = key.strip_prefix('*') {
+                self.suffixes.retain(|(s, _)| s != suffix);
+                self.suffixes.push((suffix.to_string(), code.to_string()));
+            } else {
+                self.kinds.insert(key.to_string(), code.to_string());
+            }
+        }
+    }
+
+    /// Adds a style for names with the given extension or matching the given glob. Later rules
+    /// take precedence over earlier ones.
+    pub fn add_rule(&mut self, key: &str, style: &str) -> Result<(), String> {
+        let rule = Rule::new(key)?;
+        let code = parse_style(style).map_err(|err| format!("invalid color rule '{}': {}", key, err))?;
+        self.rules.push((rule, code));
+        Ok(())
+    }
+
+    /// Replaces the colors of times by the given styles for ages such as `1h` or `2d`. Times
+    /// older than all of them aren't colored.
+    pub fn set_ages<'a>(&mut self, ages: impl IntoIterator<Item = (&'a String, &'a String)>) -> Result<(), String> {
+        let mut parsed = Vec::new();
+        for (age, style) in ages {
+            let duration = lsn::parse_duration(age).map_err(|err| format!("invalid age color '{}': {}", age, err))?;
+            let code = parse_style(style).map_err(|err| format!("invalid age color '{}': {}", age, err))?;
+            parsed.push((duration, code));
+        }
+        parsed.sort_by_key(|&(duration, _)| duration);
+        self.ages = parsed;
+        Ok(())
+    }
+
+    /// Wraps `text` in the color for a time of the given age relative to `now`, if any. Times in
+    /// the future count as new.
+    pub fn paint_age(&self, time: SystemTime, now: SystemTime, text: &str) -> String {
+        let age = now.duration_since(time).unwrap_or_default();
+        match self.ages.iter().find(|(limit, _)| age < *limit) {
+            Some((_, code)) => format!("\x1b[{}m{}\x1b[0m", code, text),
+            None => text.to_string(),
+        }
+    }
+
+    /// Whether a color is set for the given kind itself.
+    pub fn has(&self, kind: Kind) -> bool {
+        self.kinds.get(kind.key()).is_some_and(|code| !code.is_empty())
+    }
+
+    /// Returns the SGR code for an entry with the given kind and name, if any, followed by the
+    /// style of the last matching config rule.
+    pub fn code(&self, kind: Kind, name: &OsStr) -> Option<String> {
+        let file_name = Path::new(name).file_name().unwrap_or(name).to_string_lossy();
+        let rule = self.rules.iter().rev().find(|(rule, _)| rule.matches(&file_name)).map(|(_, code)| code.as_str());
+        match (self.base_code(kind, name), rule) {
+            (Some(base), Some(rule)) => Some(format!("{};{}", base, rule)),
+            (base, rule) => base.or(rule).map(str::to_string),
+        }
+    }
+
+    /// Returns the `LS_COLORS` code for an entry with the given kind and name, if any.
+    ///
+    /// Executables, FIFOs and sockets without a color of their own are colored like files.
+    fn base_code(&self, kind: Kind, name: &OsStr) -> Option<&str> {
+        let kind = match kind {
+            Kind::Executable | Kind::Fifo | Kind::Socket if !self.has(kind) => Kind::File,
+            kind => kind,
+        };
+        if kind == Kind::File {
+            let name = name.as_encoded_bytes();
+            let suffix = self.suffixes.iter().rev().find(|(s, _)| name.ends_with(s.as_bytes()));
+            if let Some((_, code)) = suffix {
+                return Some(code);
+            }
+        }
+        self.kinds.get(kind.key()).map(String::as_str).filter(|code| !code.is_empty())
+    }
+
+    /// Wraps `text` in the color for an entry with the given kind and name.
+    pub fn paint(&self, kind: Kind, name: &OsStr, text: &str) -> String {
+        match self.code(kind, name) {
+            Some(code) => format!("\x1b[{}m{}\x1b[0m", code, text),
+            None => text.to_string(),
+        }
+    }
+}
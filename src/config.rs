@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use indexmap::IndexMap;
+use serde::Deserialize;
+
+/// Settings read from the config file.
+///
+/// ```toml
+/// hide_stems = ["autosave", "*_tmp"]
+///
+/// [open]
+/// exr = "mrv2 {pattern}"
+/// mov = "mpv"
+///
+/// [colors]
+/// "3dv" = "bold yellow"
+/// "*.bgeo.sc" = "underline"
+///
+/// [ages]
+/// 1h = "bold green"
+/// 1d = "green"
+///
+/// [presets]
+/// wrangle = "-l --sort mtime:desc --sequences-first"
+/// ```
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Commands used by `lsn open`, by extension without the leading `.`.
+    pub open: HashMap<String, String>,
+    /// Styles by extension or glob, added to the colors from `LS_COLORS`, in file order.
+    pub colors: IndexMap<String, String>,
+    /// Styles of times younger than each age, such as `1h` or `2d`, replacing the defaults. An
+    /// empty table turns coloring of times off.
+    pub ages: Option<IndexMap<String, String>>,
+    /// Stems of groups hidden unless `--all` is given, as for `--hide-stem`.
+    pub hide_stems: Vec<String>,
+    /// Flags used in place of `@name` arguments, by name.
+    pub presets: HashMap<String, String>,
+}
+
+impl Config {
+    /// Location of the config file: `LSN_CONFIG`, or `lsn/config.toml` in `XDG_CONFIG_HOME`,
+    /// falling back to `~/.config`.
+    pub fn path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os("LSN_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+        let dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(dir.join("lsn").join("config.toml"))
+    }
+
+    /// Loads the config file, returning the defaults if there is none.
+    pub fn load() -> Result<Config, String> {
+        let Some(path) = Config::path() else {
+            return Ok(Config::default());
+        };
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(err) => return Err(format!("failed to read '{}': {}", path.display(), err)),
+        };
+        toml::from_str(&contents).map_err(|err| format!("invalid config '{}': {}", path.display(), err))
+    }
+
+    /// Command for opening files with the given extension, which may include the leading `.`.
+    pub fn open_command(&self, ext: &str) -> Option<&str> {
+        let ext = ext.trim_start_matches('.');
+        self.open
+            .iter()
+            .find(|(key, _)| key.trim_start_matches('.').eq_ignore_ascii_case(ext))
+            .map(|(_, command)| command.as_str())
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use glob::{MatchOptions, Pattern};
+
+/// Name of the files listing patterns of entries to hide from listings.
+pub const FILE_NAME: &str = ".lsnignore";
+
+/// A pattern from a `.lsnignore` file, in gitignore syntax.
+#[derive(Debug)]
+struct Rule {
+    pattern: Pattern,
+    /// Whether the pattern starts with `!`, showing entries hidden by earlier patterns.
+    negated: bool,
+    /// Whether the pattern ends in `/`, matching only directories.
+    dir_only: bool,
+    /// Whether the pattern contains a `/`, matching paths relative to the directory of its file
+    /// rather than names at any depth.
+    anchored: bool,
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Result<Rule, glob::PatternError>> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(line) => (true, line),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(line) => (true, line),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        Some(Pattern::new(line.trim_start_matches('/')).map(|pattern| Rule { pattern, negated, dir_only, anchored }))
+    }
+
+    /// Whether the rule matches the entry at `path` below `dir`, the directory of its file.
+    fn matches(&self, dir: &Path, path: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let options = MatchOptions { require_literal_separator: true, ..MatchOptions::new() };
+        if self.anchored {
+            path.strip_prefix(dir).is_ok_and(|rel| self.pattern.matches_path_with(rel, options))
+        } else {
+            path.file_name().is_some_and(|name| self.pattern.matches_with(&name.to_string_lossy(), options))
+        }
+    }
+}
+
+/// Patterns of `.lsnignore` files in scanned directories, each read once.
+#[derive(Debug, Default)]
+pub struct Ignores {
+    rules: Mutex<HashMap<PathBuf, Arc<Vec<Rule>>>>,
+    /// Whether directories are ignored, so their contents can be skipped quickly.
+    dirs: Mutex<HashMap<PathBuf, bool>>,
+    /// Invalid patterns found since the last call to `take_errors`.
+    errors: Mutex<Vec<String>>,
+}
+
+impl Ignores {
+    /// Rules of the `.lsnignore` file in `dir`, empty if there is none.
+    fn rules(&self, dir: &Path) -> Arc<Vec<Rule>> {
+        if let Some(rules) = lock(&self.rules).get(dir) {
+            return Arc::clone(rules);
+        }
+        let file = dir.join(FILE_NAME);
+        let rules: Vec<Rule> = std::fs::read_to_string(&file)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(Rule::parse)
+            .filter_map(|rule| rule.map_err(|err| lock(&self.errors).push(format!("invalid pattern in '{}': {}", file.display(), err))).ok())
+            .collect();
+        let rules = Arc::new(rules);
+        lock(&self.rules).insert(dir.to_owned(), Arc::clone(&rules));
+        rules
+    }
+
+    /// Takes the invalid patterns found so far, which are left out of the rules.
+    pub fn take_errors(&self) -> Vec<String> {
+        std::mem::take(&mut lock(&self.errors))
+    }
+
+    /// Whether the entry at `path`, found `depth` levels below a listed path, is hidden by the
+    /// `.lsnignore` files of the directories in between, or is inside an ignored directory.
+    ///
+    /// As in gitignore, later patterns take precedence over earlier ones and patterns in deeper
+    /// directories over those in their parents.
+    pub fn is_ignored(&self, path: &Path, depth: usize, is_dir: bool) -> bool {
+        if depth == 0 {
+            return false;
+        }
+        let Some(parent) = path.parent() else {
+            return false;
+        };
+        if depth > 1 {
+            let cached = lock(&self.dirs).get(parent).copied();
+            let parent_ignored = cached.unwrap_or_else(|| {
+                let ignored = self.is_ignored(parent, depth - 1, true);
+                lock(&self.dirs).insert(parent.to_owned(), ignored);
+                ignored
+            });
+            if parent_ignored {
+                return true;
+            }
+        }
+        let mut ignored = false;
+        for dir in path.ancestors().skip(1).take(depth).collect::<Vec<_>>().into_iter().rev() {
+            for rule in self.rules(dir).iter() {
+                if rule.matches(dir, path, is_dir) {
+                    ignored = !rule.negated;
+                }
+            }
+        }
+        ignored
+    }
+}
+
+/// Locks a cache, which stays usable even if another thread panicked while holding it.
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
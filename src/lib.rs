@@ -1,8 +1,2110 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
-use regex::Regex;
+use chrono::Datelike;
+use indexmap::IndexMap;
+use regex::bytes::Regex;
 
+pub mod ignore;
+
+use ignore::Ignores;
+
+/// Regex equivalent to the default scanner used to split numbered file names.
+pub const DEFAULT_PATTERN: &str = r"(?s-u)^(?<stem>(?:.*\D)?)(?<num>\d+)(?<ext>(?:\..*)?)$";
+
+/// Builds the regex matcher for numbered file names.
+///
+/// The regex operates on the raw encoded bytes of a file name so that names which are not valid
+/// UTF-8 are grouped without loss.
 pub fn build_regex() -> Regex {
-    Regex::new(r"^(?<stem>(?:.*\D)?)(?<num>\d+)(?<ext>(?:\..*)?)$").unwrap()
+    Regex::new(DEFAULT_PATTERN).unwrap()
+}
+
+/// Base of the numbers in file names.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Radix {
+    #[default]
+    Dec,
+    /// Hexadecimal, in which a run of hex digits needs at least one decimal digit to count as a
+    /// number so that names like `file.exe` aren't split.
+    Hex,
+    /// Hexadecimal for numbers following `0x`, decimal otherwise.
+    Auto,
+}
+
+impl Radix {
+    fn is_digit(self, b: u8) -> bool {
+        match self {
+            Radix::Dec => b.is_ascii_digit(),
+            Radix::Hex | Radix::Auto => b.is_ascii_hexdigit(),
+        }
+    }
+
+    /// Parses digits in this radix, where `Auto` is taken as decimal.
+    pub fn parse(self, digits: &[u8]) -> Option<usize> {
+        if digits.is_empty() || !digits.iter().all(|&b| self.is_digit(b)) {
+            return None;
+        }
+        let base = if self == Radix::Hex { 16 } else { 10 };
+        usize::from_str_radix(std::str::from_utf8(digits).ok()?, base).ok()
+    }
+}
+
+impl FromStr for Radix {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dec" => Ok(Radix::Dec),
+            "hex" => Ok(Radix::Hex),
+            "auto" => Ok(Radix::Auto),
+            _ => Err(format!("invalid radix '{}'", s)),
+        }
+    }
+}
+
+/// A file name split into the parts around its number.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Split<'a> {
+    pub stem: &'a [u8],
+    pub num: &'a [u8],
+    pub ext: &'a [u8],
+    /// Radix of the number, either `Dec` or `Hex`.
+    pub radix: Radix,
+}
+
+/// Largest magnitude of a number that is grouped, such as a frame number. Longer digit runs, such
+/// as 40 digit IDs, are kept far enough from the limits of `isize` that ranges and the gaps
+/// between numbers can be computed without overflow.
+pub const MAX_NUMBER: usize = 999_999_999_999_999_999;
+
+impl Split<'_> {
+    /// Parses the number, which has a leading `-` if it is negative, returning `None` if it has
+    /// invalid digits or is larger than `MAX_NUMBER`.
+    pub fn number(&self) -> Option<isize> {
+        let (digits, sign) = match self.num.strip_prefix(b"-") {
+            Some(digits) => (digits, -1),
+            None => (self.num, 1),
+        };
+        let n = self.radix.parse(digits).filter(|&n| n <= MAX_NUMBER)?;
+        Some(sign * n as isize)
+    }
+}
+
+/// Strategy for splitting file names into a stem, number and extension.
+///
+/// By default a byte scanner splits names around the last run of digits that ends the name or is
+/// followed by a `.`, equivalent to `DEFAULT_PATTERN` but much faster.
+#[derive(Clone, Debug, Default)]
+pub struct Matcher {
+    /// A regex with `stem`, `num` and `ext` named groups used instead of the scanner.
+    regex: Option<Regex>,
+    radix: Radix,
+    /// Whether a `-` right before the number is its sign rather than part of the stem.
+    signed: bool,
+}
+
+impl Matcher {
+    /// Builds a regex matcher, checking that the pattern has the required named groups.
+    pub fn from_pattern(pattern: &str) -> Result<Matcher, String> {
+        let regex = Regex::new(pattern).map_err(|e| e.to_string())?;
+        for group in ["stem", "num", "ext"] {
+            if !regex.capture_names().any(|name| name == Some(group)) {
+                return Err(format!("pattern is missing a named group '{}'", group));
+            }
+        }
+        Ok(Matcher { regex: Some(regex), radix: Radix::Dec, signed: false })
+    }
+
+    /// Sets the radix of numbers to look for.
+    pub fn with_radix(self, radix: Radix) -> Matcher {
+        Matcher { radix, ..self }
+    }
+
+    /// Sets whether numbers may be negative, as in `frame-0010.exr`.
+    pub fn with_signed(self, signed: bool) -> Matcher {
+        Matcher { signed, ..self }
+    }
+
+    pub fn split<'a>(&self, name: &'a [u8]) -> Option<Split<'a>> {
+        match self.radix {
+            Radix::Auto => self
+                .split_in(name, Radix::Hex)
+                .filter(|split| split.stem.ends_with(b"0x") || split.stem.ends_with(b"0X"))
+                .or_else(|| self.split_in(name, Radix::Dec)),
+            radix => self.split_in(name, radix),
+        }
+    }
+
+    fn split_in<'a>(&self, name: &'a [u8], radix: Radix) -> Option<Split<'a>> {
+        match &self.regex {
+            None => {
+                // Runs of hex letters alone, like the `cafe` of `shot.0a1f.cafe`, are words rather
+                // than numbers, so the scan moves on to earlier runs.
+                let (start, end) = (1..=name.len())
+                    .rev()
+                    .filter(|&e| (e == name.len() || name[e] == b'.') && radix.is_digit(name[e - 1]))
+                    .map(|end| (name[..end].iter().rposition(|&b| !radix.is_digit(b)).map_or(0, |i| i + 1), end))
+                    .find(|&(start, end)| name[start..end].iter().any(u8::is_ascii_digit))?;
+                Some(self.signed_split(name, 0..start, start..end, end..name.len(), radix))
+            }
+            Some(regex) => {
+                let caps = regex.captures(name)?;
+                let range = |group| caps.name(group).map_or(0..0, |m| m.range());
+                Some(self.signed_split(name, range("stem"), range("num"), range("ext"), radix))
+            }
+        }
+    }
+
+    /// Builds a split from byte ranges of `name`, moving a `-` at the end of the stem into the
+    /// number for signed matchers.
+    fn signed_split<'a>(
+        &self,
+        name: &'a [u8],
+        mut stem: std::ops::Range<usize>,
+        mut num: std::ops::Range<usize>,
+        ext: std::ops::Range<usize>,
+        radix: Radix,
+    ) -> Split<'a> {
+        if self.signed && stem.end == num.start && stem.end > stem.start && name[stem.end - 1] == b'-' {
+            stem.end -= 1;
+            num.start -= 1;
+        }
+        Split { stem: &name[stem], num: &name[num], ext: &name[ext], radix }
+    }
+}
+
+/// A file name split around a date token.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DateSplit<'a> {
+    pub stem: &'a [u8],
+    pub ext: &'a [u8],
+    pub date: chrono::NaiveDate,
+}
+
+/// Finds the last `YYYYMMDD` or `YYYY-MM-DD` date token in a name that isn't part of a longer run
+/// of digits.
+pub fn split_date(name: &[u8]) -> Option<DateSplit<'_>> {
+    let is_digit = |i: usize| name.get(i).is_some_and(u8::is_ascii_digit);
+    let digits = |range: std::ops::Range<usize>| -> Option<u32> { std::str::from_utf8(&name[range]).ok()?.parse().ok() };
+    (0..name.len()).rev().find_map(|start| {
+        if start > 0 && is_digit(start - 1) {
+            return None;
+        }
+        let (end, month, day) = if (start..start + 8).all(is_digit) {
+            (start + 8, start + 4, start + 6)
+        } else if (start..start + 4).chain(start + 5..start + 7).chain(start + 8..start + 10).all(is_digit)
+            && name[start + 4] == b'-'
+            && name[start + 7] == b'-'
+        {
+            (start + 10, start + 5, start + 8)
+        } else {
+            return None;
+        };
+        if is_digit(end) {
+            return None;
+        }
+        let year = digits(start..start + 4)? as i32;
+        let date = chrono::NaiveDate::from_ymd_opt(year, digits(month..month + 2)?, digits(day..day + 2)?)?;
+        Some(DateSplit { stem: &name[..start], ext: &name[end..], date })
+    })
+}
+
+/// A file name split around its trailing number.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NumberedName<'a> {
+    /// Everything before the number.
+    pub stem: &'a str,
+    pub number: usize,
+    /// Width of the number as written, including any leading zeros.
+    pub pad: usize,
+    /// Everything after the number, starting with `.` if not empty.
+    pub ext: &'a str,
+}
+
+/// Splits a file name like `frame0042.exr` into its stem, number, padding and extension.
+///
+/// Returns `None` if the name has no trailing number or the number doesn't fit in a `usize`.
+pub fn parse_numbered_name(name: &str) -> Option<NumberedName<'_>> {
+    let split = Matcher::default().split(name.as_bytes())?;
+    let (stem_len, num_len) = (split.stem.len(), split.num.len());
+    Some(NumberedName {
+        stem: &name[..stem_len],
+        number: usize::try_from(split.number()?).ok()?,
+        pad: num_len,
+        ext: &name[stem_len + num_len..],
+    })
+}
+
+/// Metadata of an entry, read from the filesystem or given to a `Scanner`, such as from a tar
+/// header or an object listing.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Meta {
+    pub modified: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
+    pub created: Option<SystemTime>,
+    pub size: u64,
+    pub is_dir: bool,
+    pub is_symlink: bool,
+    /// Whether the file is a regular file with any execute permission bit set.
+    pub is_executable: bool,
+    pub is_fifo: bool,
+    pub is_socket: bool,
+    /// Device and inode number identifying the file, where supported.
+    pub inode: Option<(u64, u64)>,
+    /// Number of hard links to the file, where supported.
+    pub nlink: Option<u64>,
+    /// Whether other entries may be the same file: it has several hard links, or is the target
+    /// of a dereferenced symbolic link.
+    pub shared: bool,
+    /// User and group IDs of the owner where supported, or for groups of files, if all files
+    /// share them.
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+}
+
+impl From<std::fs::Metadata> for Meta {
+    fn from(value: std::fs::Metadata) -> Self {
+        #[cfg(unix)]
+        let (inode, nlink, is_executable, is_fifo, is_socket, uid, gid) = {
+            use std::os::unix::fs::{FileTypeExt, MetadataExt};
+            let file_type = value.file_type();
+            (
+                Some((value.dev(), value.ino())),
+                Some(value.nlink()),
+                value.is_file() && value.mode() & 0o111 != 0,
+                file_type.is_fifo(),
+                file_type.is_socket(),
+                Some(value.uid()),
+                Some(value.gid()),
+            )
+        };
+        #[cfg(not(unix))]
+        let (inode, nlink, is_executable, is_fifo, is_socket, uid, gid) = (None, None, false, false, false, None, None);
+        Meta {
+            modified: value.modified().ok(),
+            accessed: value.accessed().ok(),
+            created: value.created().ok(),
+            size: value.len(),
+            is_dir: value.is_dir(),
+            is_symlink: value.is_symlink(),
+            is_executable,
+            is_fifo,
+            is_socket,
+            inode,
+            shared: nlink.is_some_and(|n| n > 1),
+            nlink,
+            uid,
+            gid,
+        }
+    }
+}
+
+impl Meta {
+    /// Device of the filesystem the file is on.
+    pub fn device(&self) -> Option<u64> {
+        self.inode.map(|(dev, _)| dev)
+    }
+    /// Whether this file is the same as `other` through a hard link or dereferenced symbolic link.
+    pub fn same_inode(&self, other: &Meta) -> bool {
+        (self.shared || other.shared) && self.inode.is_some() && self.inode == other.inode
+    }
+}
+
+/// A single numbered file belonging to a `FileGroup`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Member {
+    pub number: isize,
+    /// Original file name, including any zero padding.
+    pub name: OsString,
+    pub meta: Option<Meta>,
+}
+
+/// Entries in a directory sharing a stem and extension around their numbers, or a single entry
+/// without a number.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileGroup {
+    /// Range of numbers in files with the same root and extension.
+    pub range: Option<std::ops::Range<isize>>,
+    /// All files in the group in the order they were found.
+    pub members: Vec<Member>,
+    /// Whether the number is a trailing version token (e.g. `_v001`) rather than a frame number.
+    pub versioned: bool,
+    pub parent: Option<PathBuf>,
+    pub stem: OsString,
+    pub ext: OsString,
+    pub meta: Option<Meta>,
+    /// Zero padding width shared by all members, set when groups are split by padding. Zero
+    /// means the numbers are not padded.
+    pub pad: Option<usize>,
+    /// Radix the members are numbered in, either `Dec` or `Hex`.
+    pub radix: Radix,
+    /// Whether members are numbered by a date in their name, as days since the common era.
+    pub dated: bool,
+    /// All extensions of a group merged from sequences that differ only in extension, or empty.
+    pub exts: Vec<OsString>,
+    /// All stems of a group merged from sequences whose stems differ only in trailing separators,
+    /// or empty.
+    pub stems: Vec<OsString>,
+    /// Modulus of a counter that wraps around to 0, as set by `Scanner::with_wrap`.
+    pub wrap: Option<usize>,
+}
+
+impl FileGroup {
+    /// Creates an empty group of numbered files to be populated with `add`.
+    pub fn numbered(parent: Option<PathBuf>, stem: OsString, ext: OsString, versioned: bool) -> Self {
+        FileGroup { range: None, members: Vec::new(), versioned, parent, stem, ext, meta: None, pad: None, radix: Radix::Dec, dated: false, exts: Vec::new(), stems: Vec::new(), wrap: None }
+    }
+    /// Adds a numbered file to the group, updating its range and aggregate metadata.
+    pub fn add(&mut self, member: Member) {
+        let num = member.number;
+        // Update range
+        let range = self.range.get_or_insert(num..num+1);
+        range.start = range.start.min(num);
+        range.end = range.end.max(num+1);
+
+        match (self.meta.as_mut(), member.meta.as_ref()) {
+            (Some(grp_meta), Some(meta)) => {
+                // Update last modified and last accessed metadata, keeping times that are only
+                // known for some members.
+                grp_meta.modified = grp_meta.modified.max(meta.modified);
+                grp_meta.accessed = grp_meta.accessed.max(meta.accessed);
+                grp_meta.created = match (grp_meta.created, meta.created) {
+                    (Some(grp_created), Some(cur_created)) => Some(grp_created.min(cur_created)),
+                    (grp_created, cur_created) => grp_created.or(cur_created),
+                };
+                // Links to a file already in the group take no additional space.
+                let linked = meta.shared
+                    && self.members.iter().any(|m| m.meta.as_ref().is_some_and(|m| m.same_inode(meta)));
+                if !linked {
+                    grp_meta.size += meta.size;
+                }
+                // Sequences are only shown as executable if all of their files are.
+                grp_meta.is_executable &= meta.is_executable;
+                if grp_meta.uid != meta.uid {
+                    grp_meta.uid = None;
+                }
+                if grp_meta.gid != meta.gid {
+                    grp_meta.gid = None;
+                }
+            }
+            (None, Some(meta)) if self.members.is_empty() => self.meta = Some(meta.clone()),
+            _ => {}
+        }
+        self.members.push(member);
+    }
+    /// Keeps only the members for which `f` returns `true`, recomputing the range and aggregates.
+    pub fn retain_members(&mut self, f: impl FnMut(&Member) -> bool) {
+        let members = std::mem::take(&mut self.members);
+        self.range = None;
+        self.meta = None;
+        for member in members.into_iter().filter(f) {
+            self.add(member);
+        }
+    }
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.meta.as_ref().and_then(|meta| meta.modified)
+    }
+    pub fn accessed(&self) -> Option<SystemTime> {
+        self.meta.as_ref().and_then(|meta| meta.accessed)
+    }
+    pub fn created(&self) -> Option<SystemTime> {
+        self.meta.as_ref().and_then(|meta| meta.created)
+    }
+    pub fn size(&self) -> Option<u64> {
+        self.meta.as_ref().map(|meta| meta.size)
+    }
+    pub fn uid(&self) -> Option<u32> {
+        self.meta.as_ref().and_then(|meta| meta.uid)
+    }
+    pub fn gid(&self) -> Option<u32> {
+        self.meta.as_ref().and_then(|meta| meta.gid)
+    }
+    pub fn is_dir(&self) -> bool {
+        self.meta.as_ref().map(|meta| meta.is_dir).unwrap_or(false)
+    }
+    pub fn is_symlink(&self) -> bool {
+        self.meta.as_ref().map(|meta| meta.is_symlink).unwrap_or(false)
+    }
+    /// Inode number of a single file, or `None` for sequences.
+    pub fn inode(&self) -> Option<u64> {
+        if self.is_sequence() {
+            return None;
+        }
+        self.meta.as_ref().and_then(|meta| meta.inode).map(|(_, ino)| ino)
+    }
+    /// Number of members that are hard links to another member of the group.
+    pub fn hard_linked(&self) -> usize {
+        let mut seen = HashSet::new();
+        self.members
+            .iter()
+            .filter_map(|m| m.meta.as_ref())
+            .filter(|meta| meta.nlink.is_some_and(|n| n > 1))
+            .filter_map(|meta| meta.inode)
+            .filter(|inode| !seen.insert(*inode))
+            .count()
+    }
+    /// Plans renaming every member to the name given by `name`, within the group's directory.
+    pub fn rename_plan(&self, name: impl Fn(&Member) -> OsString) -> Result<RenamePlan, String> {
+        let parent = self.parent.clone().unwrap_or_default();
+        let mut members: Vec<_> = self.members.iter().collect();
+        members.sort_by_key(|m| m.number);
+        RenamePlan::new(members.into_iter().map(|m| (parent.join(&m.name), parent.join(name(m)))))
+    }
+    /// Name shown for the group: the pattern with a `#` placeholder for sequences, and the file
+    /// name otherwise.
+    pub fn name(&self) -> OsString {
+        self.name_in(PatternStyle::Lsn)
+    }
+    /// Directory of the group joined with its name, identifying it within a listing.
+    pub fn path(&self) -> OsString {
+        self.parent.clone().unwrap_or_default().join(self.name()).into_os_string()
+    }
+    /// Name shown for the group with the number of sequences written in the given style.
+    pub fn name_in(&self, style: PatternStyle) -> OsString {
+        if self.is_sequence() {
+            let mut name = self.stem_label();
+            name.push(self.placeholder_in(style));
+            name.push(self.ext_label());
+            name
+        } else if let Some(member) = self.members.first() {
+            member.name.clone()
+        } else {
+            let mut name = self.stem.clone();
+            name.push(&self.ext);
+            name
+        }
+    }
+    /// Extension shown after the placeholder: the extension, or a list such as `{.exr,.jpg}` for
+    /// merged groups.
+    pub fn ext_label(&self) -> OsString {
+        if self.exts.is_empty() {
+            return self.ext.clone();
+        }
+        let mut label = OsString::from(" {");
+        for (i, ext) in self.exts.iter().enumerate() {
+            if i > 0 {
+                label.push(",");
+            }
+            label.push(ext);
+        }
+        label.push("}");
+        label
+    }
+    /// Stem shown before the placeholder: the stem, or one such as `frame{.,_}` listing the
+    /// separators of groups merged by `--separators`.
+    pub fn stem_label(&self) -> OsString {
+        if self.stems.is_empty() {
+            return self.stem.clone();
+        }
+        let common = self.common_stem();
+        let mut label = os_string_from_bytes(common);
+        label.push("{");
+        for (i, stem) in self.stems.iter().enumerate() {
+            if i > 0 {
+                label.push(",");
+            }
+            label.push(os_string_from_bytes(&stem.as_encoded_bytes()[common.len()..]));
+        }
+        label.push("}");
+        label
+    }
+    /// Stem without a trailing `.`, `_` or `-`, as matched by `--name` and `--hide-stem`.
+    pub fn bare_stem(&self) -> &[u8] {
+        let stem = self.stem.as_encoded_bytes();
+        stem.strip_suffix(b".").or_else(|| stem.strip_suffix(b"_")).or_else(|| stem.strip_suffix(b"-")).unwrap_or(stem)
+    }
+    /// Start shared by all stems of a group merged by `--separators`, or the whole stem.
+    pub fn common_stem(&self) -> &[u8] {
+        self.stems.iter().fold(self.stem.as_encoded_bytes(), |common, stem| {
+            let len = common.iter().zip(stem.as_encoded_bytes()).take_while(|(a, b)| a == b).count();
+            &common[..len]
+        })
+    }
+    /// Merges in a sequence whose stem differs only in its trailing separators.
+    pub fn merge_stem(&mut self, other: FileGroup) {
+        if self.stems.is_empty() {
+            self.stems.push(self.stem.clone());
+        }
+        self.stems.push(other.stem);
+        self.stems.sort();
+        for member in other.members {
+            self.add(member);
+        }
+    }
+    /// Merges in a sequence with the same stem but a different extension.
+    pub fn merge(&mut self, other: FileGroup) {
+        if self.exts.is_empty() {
+            self.exts.push(self.ext.clone());
+        }
+        self.exts.push(other.ext);
+        self.exts.sort();
+        for member in other.members {
+            self.add(member);
+        }
+    }
+    /// Number of frames missing for each extension of a merged group, compared to all extensions
+    /// together.
+    pub fn missing_per_ext(&self) -> Vec<(&OsString, usize)> {
+        let mut frames = self.frames();
+        frames.sort_unstable();
+        frames.dedup();
+        self.exts
+            .iter()
+            .map(|ext| {
+                let count = self.members.iter().filter(|m| m.name.as_encoded_bytes().ends_with(ext.as_encoded_bytes())).count();
+                (ext, frames.len().saturating_sub(count))
+            })
+            .filter(|&(_, missing)| missing > 0)
+            .collect()
+    }
+    /// Printf-style pattern for the members' names, e.g. `frame.%04d.exr`.
+    pub fn printf_pattern(&self) -> OsString {
+        self.canonical_pattern(PatternStyle::Printf)
+    }
+    /// Pattern for the members' names with the number written in the given style, e.g.
+    /// `frame.####.exr` or `frame.$F4.exr`. Dated groups use a `<date>` placeholder.
+    pub fn canonical_pattern(&self, style: PatternStyle) -> OsString {
+        let mut pattern = self.stem.clone();
+        pattern.push(self.placeholder_in(style));
+        pattern.push(&self.ext);
+        pattern
+    }
+    /// Placeholder shown in place of the number in the given style.
+    pub fn placeholder_in(&self, style: PatternStyle) -> String {
+        if style == PatternStyle::Lsn || self.dated {
+            return self.placeholder();
+        }
+        let conversion = match self.radix {
+            Radix::Hex if self.upper_hex() => 'X',
+            Radix::Hex => 'x',
+            _ => 'd',
+        };
+        number_placeholder(style, self.pad_width(), conversion)
+    }
+    /// Zero padding width of the members, or 0 if none are zero padded.
+    pub fn pad_width(&self) -> usize {
+        let padded = self.members.iter().any(|m| {
+            let digits = self.digits(m);
+            digits.len() > 1 && digits[0] == b'0'
+        });
+        if padded {
+            self.pad.unwrap_or_else(|| self.padding())
+        } else {
+            0
+        }
+    }
+    /// Whether any member's number has upper case hex digits.
+    fn upper_hex(&self) -> bool {
+        self.members.iter().any(|m| self.digits(m).iter().any(u8::is_ascii_uppercase))
+    }
+    /// Formats a number in the group's radix, zero padded to `width` digits. Hex digits are upper
+    /// case if any member uses upper case.
+    pub fn format_number(&self, n: isize, width: usize) -> String {
+        if self.dated {
+            let date = i32::try_from(n).ok().and_then(chrono::NaiveDate::from_num_days_from_ce_opt);
+            return date.map(|date| date.format("%Y-%m-%d").to_string()).unwrap_or_default();
+        }
+        if n < 0 {
+            return format!("-{}", self.format_number(-n, width));
+        }
+        match self.radix {
+            Radix::Hex if self.upper_hex() => format!("{:0width$X}", n),
+            Radix::Hex => format!("{:0width$x}", n),
+            _ => format!("{:0width$}", n),
+        }
+    }
+    /// Number of files in the group.
+    pub fn count(&self) -> usize {
+        self.members.len().max(1)
+    }
+    /// Numbers of all files in the group in the order they were found.
+    pub fn frames(&self) -> Vec<isize> {
+        self.members.iter().map(|m| m.number).collect()
+    }
+    /// The member with the highest number.
+    pub fn latest(&self) -> Option<&Member> {
+        self.members.iter().max_by_key(|m| m.number)
+    }
+    /// Path of the member with the lowest number, or of the entry itself if it isn't numbered.
+    pub fn first_path(&self) -> PathBuf {
+        let name = match self.members.iter().min_by_key(|m| m.number) {
+            Some(member) => member.name.clone(),
+            None => {
+                let mut name = self.stem.clone();
+                name.push(&self.ext);
+                name
+            }
+        };
+        self.parent.clone().unwrap_or_default().join(name)
+    }
+    /// Smallest number of digits used by any member, which is the zero padding of a consistently
+    /// padded sequence.
+    pub fn padding(&self) -> usize {
+        self.members.iter().map(|m| self.digits(m).len()).min().unwrap_or(0)
+    }
+    /// Size statistics of the group's files, which exclude directories.
+    pub fn size_stats(&self) -> Option<SizeStats> {
+        let sizes = self.members.iter().filter_map(|m| m.meta.as_ref()).filter(|meta| !meta.is_dir).map(|meta| meta.size);
+        SizeStats::new(sizes)
+    }
+    /// Number of frames missing from the group's span.
+    pub fn missing_count(&self) -> usize {
+        let mut frames = self.frames();
+        frames.sort_unstable();
+        frames.dedup();
+        self.span_len() - frames.len()
+    }
+    /// Modulus of the group's counter if it wraps and all numbers are within it.
+    fn modulus(&self) -> Option<isize> {
+        let modulus = self.wrap.and_then(|n| isize::try_from(n).ok())?;
+        self.range.as_ref().filter(|range| range.start >= 0 && range.end <= modulus).map(|_| modulus)
+    }
+    /// First and last numbers of the group, which for counters wrapping with `--wrap` are those
+    /// of the logical contiguous span, so the first may be larger than the last.
+    pub fn span(&self) -> Option<(isize, isize)> {
+        match self.modulus() {
+            Some(modulus) => wrapped_span(self.frames(), modulus),
+            None => self.range.as_ref().map(|range| (range.start, range.end - 1)),
+        }
+    }
+    /// Number of numbers in the group's span.
+    pub fn span_len(&self) -> usize {
+        match (self.span(), self.modulus()) {
+            (Some((first, last)), Some(modulus)) => (last - first).rem_euclid(modulus) as usize + 1,
+            (Some((first, last)), None) => (last - first) as usize + 1,
+            _ => 0,
+        }
+    }
+    /// Ranges of numbers missing from the group's span, as inclusive `(start, end)` pairs. A gap
+    /// of a wrapping counter may run across the wrap point, so its start is larger than its end.
+    pub fn gaps(&self) -> Vec<(isize, isize)> {
+        let (Some((first, _)), Some(modulus)) = (self.span(), self.modulus()) else {
+            return gaps(self.frames());
+        };
+        let unwrapped = self.frames().into_iter().map(|n| if n < first { n + modulus } else { n });
+        gaps(unwrapped).into_iter().map(|(start, end)| (start % modulus, end % modulus)).collect()
+    }
+    /// Placeholder shown in place of the number: one `#` per digit for zero padded groups split
+    /// by `Scanner::with_split_padding`, and a single `#` otherwise.
+    pub fn placeholder(&self) -> String {
+        if self.dated {
+            return "<date>".to_string();
+        }
+        "#".repeat(self.pad.unwrap_or(1).max(1))
+    }
+    /// Extension of a member, which differs between members of merged groups.
+    fn member_ext<'a>(&'a self, member: &Member) -> &'a [u8] {
+        let name = member.name.as_encoded_bytes();
+        std::iter::once(&self.ext)
+            .chain(self.exts.iter())
+            .map(|ext| ext.as_encoded_bytes())
+            .filter(|ext| name.ends_with(ext))
+            .max_by_key(|ext| ext.len())
+            .unwrap_or_default()
+    }
+    /// The stem a member's name starts with, which differs for groups merged by `--separators`.
+    fn member_stem<'a>(&'a self, member: &Member) -> &'a [u8] {
+        let name = member.name.as_encoded_bytes();
+        std::iter::once(&self.stem)
+            .chain(self.stems.iter())
+            .map(|stem| stem.as_encoded_bytes())
+            .filter(|stem| name.starts_with(stem))
+            .max_by_key(|stem| stem.len())
+            .unwrap_or_default()
+    }
+    /// Digits of a member's number, without any sign.
+    fn digits<'a>(&self, member: &'a Member) -> &'a [u8] {
+        let name = member.name.as_encoded_bytes();
+        let digits = &name[self.member_stem(member).len()..name.len() - self.member_ext(member).len()];
+        digits.strip_prefix(b"-").unwrap_or(digits)
+    }
+    /// Numbers shared by differently named members with the same extension, such as
+    /// `frame001.exr` and `frame1.exr`, with the names of those members in sorted order.
+    pub fn duplicates(&self) -> Vec<(isize, Vec<&OsString>)> {
+        let mut by_number: IndexMap<(isize, &[u8]), Vec<&OsString>> = IndexMap::new();
+        for member in self.members.iter() {
+            let names = by_number.entry((member.number, self.member_ext(member))).or_default();
+            if !names.contains(&&member.name) {
+                names.push(&member.name);
+            }
+        }
+        let mut duplicates: Vec<_> =
+            by_number.into_iter().filter(|(_, names)| names.len() > 1).map(|((number, _), mut names)| { names.sort(); (number, names) }).collect();
+        duplicates.sort_by_key(|&(number, _)| number);
+        duplicates
+    }
+    /// Splits the group into groups whose members share the same zero padding.
+    ///
+    /// Zero padded numbers are grouped by their width. Numbers without leading zeros join the
+    /// padded group of the same width if there is one, and are otherwise considered unpadded.
+    pub fn split_padding(self) -> Vec<FileGroup> {
+        if self.range.is_none() {
+            return vec![self];
+        }
+        let padded = |digits: &[u8]| digits.len() > 1 && digits[0] == b'0';
+        let widths: BTreeSet<_> = self.members.iter()
+            .map(|m| self.digits(m))
+            .filter(|d| padded(d))
+            .map(|d| d.len())
+            .collect();
+        let mut groups: IndexMap<usize, FileGroup> = IndexMap::new();
+        for member in self.members.iter() {
+            let digits = self.digits(member);
+            let pad = if padded(digits) || widths.contains(&digits.len()) { digits.len() } else { 0 };
+            groups.entry(pad).or_insert_with(|| {
+                let mut grp = FileGroup::numbered(self.parent.clone(), self.stem.clone(), self.ext.clone(), self.versioned);
+                grp.pad = Some(pad);
+                grp.radix = self.radix;
+                grp.dated = self.dated;
+                grp.wrap = self.wrap;
+                grp
+            }).add(member.clone());
+        }
+        groups.into_values().collect()
+    }
+    /// Distinct zero padding widths of the members in increasing order, where 0 means unpadded.
+    pub fn paddings(&self) -> Vec<usize> {
+        let mut pads: Vec<_> = self.clone().split_padding().into_iter().filter_map(|grp| grp.pad).collect();
+        pads.sort_unstable();
+        pads
+    }
+    /// Formats ranges of numbers such as those from `gaps` compactly, e.g. `5,10-12`.
+    pub fn format_gaps(&self, gaps: &[(isize, isize)]) -> String {
+        let gaps: Vec<_> = gaps
+            .iter()
+            .map(|&(start, end)| {
+                if start == end {
+                    self.format_number(start, 0)
+                } else {
+                    format!("{}-{}", self.format_number(start, 0), self.format_number(end, 0))
+                }
+            })
+            .collect();
+        gaps.join(",")
+    }
+    /// Whether this group is displayed as a collapsed range of more than one number.
+    pub fn is_sequence(&self) -> bool {
+        self.range.as_ref().is_some_and(|range| range.len() > 1)
+    }
+}
+
+/// Groups numbered names of entries from any source, such as a walk of the filesystem, tar
+/// archives, object storage listings or database rows, into sequences per directory.
+///
+/// Like `lsn` without options, hidden entries are skipped and numbered directories are not
+/// grouped by default.
+#[derive(Clone, Debug, Default)]
+pub struct Scanner {
+    matcher: Matcher,
+    groups: IndexMap<OsString, FileGroup>,
+    /// Whether hidden entries are kept.
+    hidden: bool,
+    /// Rules of the `.lsnignore` files read so far, if they are used. Copies of a scanner share
+    /// them.
+    ignores: Option<Arc<Ignores>>,
+    /// Whether every entry is kept on its own, without grouping numbered names.
+    classic: bool,
+    group_dirs: bool,
+    dates: bool,
+    versions: bool,
+    wrap: Option<usize>,
+    split_padding: bool,
+}
+
+impl Scanner {
+    /// Creates a scanner splitting names with the given matcher.
+    pub fn new(matcher: Matcher) -> Scanner {
+        Scanner { matcher, ..Scanner::default() }
+    }
+
+    /// Sets whether hidden entries, those starting with `.` and on Windows and macOS those with
+    /// the hidden attribute, are kept.
+    pub fn with_hidden(self, hidden: bool) -> Scanner {
+        Scanner { hidden, ..self }
+    }
+
+    /// Sets whether entries matching the patterns of `.lsnignore` files in the directories of
+    /// the entries added are skipped.
+    pub fn with_ignore_files(self, ignore_files: bool) -> Scanner {
+        Scanner { ignores: ignore_files.then(Default::default), ..self }
+    }
+
+    /// Sets whether every entry is kept on its own, like `ls` does.
+    pub fn with_classic(self, classic: bool) -> Scanner {
+        Scanner { classic, ..self }
+    }
+
+    /// Sets whether numbered directories are grouped like files.
+    pub fn with_group_dirs(self, group_dirs: bool) -> Scanner {
+        Scanner { group_dirs, ..self }
+    }
+
+    /// Sets whether names containing a date are grouped by the text around it, numbered by day.
+    pub fn with_dates(self, dates: bool) -> Scanner {
+        Scanner { dates, ..self }
+    }
+
+    /// Sets whether groups whose number is a trailing version token, as in `comp_v001.nk`, are
+    /// marked as versioned.
+    pub fn with_versions(self, versions: bool) -> Scanner {
+        Scanner { versions, ..self }
+    }
+
+    /// Sets the modulus of counters that wrap around to 0.
+    pub fn with_wrap(self, wrap: Option<usize>) -> Scanner {
+        Scanner { wrap, ..self }
+    }
+
+    /// Sets whether groups are split by the zero padding of their numbers.
+    pub fn with_split_padding(self, split_padding: bool) -> Scanner {
+        Scanner { split_padding, ..self }
+    }
+
+    /// Groups the given entries with the default settings.
+    pub fn from_entries(entries: impl Iterator<Item = (PathBuf, Option<Meta>)>) -> Scanner {
+        let mut scanner = Scanner::default();
+        scanner.extend(entries);
+        scanner
+    }
+
+    /// Adds an entry to its group, taking each component of its path as a level below the listed
+    /// path.
+    pub fn add(&mut self, path: PathBuf, meta: Option<Meta>) {
+        let is_dir = meta.as_ref().is_some_and(|meta| meta.is_dir);
+        self.add_entry(&path, path.components().count(), is_dir, || meta);
+    }
+
+    /// Adds the entry at `path`, found `depth` levels below a listed path, to its group. Its
+    /// metadata is only read if the entry isn't skipped.
+    pub fn add_entry(&mut self, path: &Path, depth: usize, is_dir: bool, meta: impl FnOnce() -> Option<Meta>) {
+        let parent = path.parent().map(ToOwned::to_owned);
+        // Paths without a file name, such as `/` or `..`, are named by the path itself.
+        let stem = path.file_stem().map_or_else(|| path.as_os_str().to_owned(), ToOwned::to_owned);
+        // Listed paths are kept even if hidden.
+        if !self.hidden && depth > 0 && (stem.as_encoded_bytes().first() == Some(&b'.') || has_hidden_attribute(path)) {
+            return;
+        }
+        if self.ignores.as_ref().is_some_and(|ignores| ignores.is_ignored(path, depth, is_dir)) {
+            return;
+        }
+        let extension = path.extension().map(ToOwned::to_owned).unwrap_or(OsString::from(""));
+        let mut ext = OsString::new();
+        if !extension.is_empty() {
+            ext.push(".");
+            ext.push(extension);
+        }
+        let file_name = path.file_name().map(ToOwned::to_owned).unwrap_or(OsString::from(".."));
+        // Numbered directories are only grouped on request, and nothing is in classic mode.
+        let groupable = !self.classic && (self.group_dirs || !is_dir);
+        let name = file_name.as_encoded_bytes();
+        // Dates are numbered by day so that gaps are missing days.
+        let parts = match self.dates.then(|| split_date(name)).flatten() {
+            Some(split) => Some((split.stem, split.ext, split.date.num_days_from_ce() as isize, Radix::Dec, true)),
+            None => self.matcher.split(name).and_then(|split| split.number().map(|num| (split.stem, split.ext, num, split.radix, false))),
+        };
+        let Some((stem_bytes, ext_bytes, num, radix, dated)) = parts.filter(|_| groupable) else {
+            // Default range of size one will be treated as a single file and not a group anyways.
+            let grp = FileGroup { meta: meta(), ..FileGroup::numbered(parent, stem, ext, false) };
+            self.groups.insert(path.as_os_str().to_owned(), grp);
+            return;
+        };
+
+        let stem = os_string_from_bytes(stem_bytes);
+        let ext = os_string_from_bytes(ext_bytes);
+        let versioned = !dated && self.versions && is_version_stem(stem_bytes);
+        // Groups are formed per directory.
+        let mut key_name = stem.clone();
+        key_name.push(if dated { "<date>" } else { "#" });
+        key_name.push(&ext);
+        let key = parent.clone().unwrap_or_default().join(key_name).into_os_string();
+        let member = Member { number: num, name: file_name, meta: meta() };
+        let wrap = self.wrap.filter(|_| !dated);
+        self.groups
+            .entry(key)
+            .or_insert_with(|| FileGroup { radix, dated, wrap, ..FileGroup::numbered(parent, stem, ext, versioned) })
+            .add(member);
+    }
+
+    /// Groups in the order their first entries were added.
+    pub fn groups(&self) -> impl Iterator<Item = &FileGroup> {
+        self.groups.values()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Groups in the order their first entries were added, split by padding if requested.
+    pub fn into_groups(self) -> Vec<FileGroup> {
+        let groups = self.groups.into_values();
+        if self.split_padding {
+            groups.flat_map(FileGroup::split_padding).collect()
+        } else {
+            groups.collect()
+        }
+    }
+
+    /// Takes the problems found so far, such as invalid patterns in `.lsnignore` files, whose
+    /// entries were not skipped.
+    pub fn take_errors(&self) -> Vec<String> {
+        self.ignores.as_ref().map(|ignores| ignores.take_errors()).unwrap_or_default()
+    }
+}
+
+impl Extend<(PathBuf, Option<Meta>)> for Scanner {
+    fn extend<I: IntoIterator<Item = (PathBuf, Option<Meta>)>>(&mut self, entries: I) {
+        for (path, meta) in entries {
+            self.add(path, meta);
+        }
+    }
+}
+
+/// Whether the file at `path` is marked hidden by the `FILE_ATTRIBUTE_HIDDEN` attribute on
+/// Windows or the `UF_HIDDEN` flag on macOS. Always false elsewhere.
+fn has_hidden_attribute(path: &Path) -> bool {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        path.symlink_metadata().is_ok_and(|meta| meta.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0)
+    }
+    #[cfg(target_os = "macos")]
+    {
+        use std::os::macos::fs::MetadataExt;
+        const UF_HIDDEN: u32 = 0x8000;
+        path.symlink_metadata().is_ok_and(|meta| meta.st_flags() & UF_HIDDEN != 0)
+    }
+    #[cfg(not(any(windows, target_os = "macos")))]
+    {
+        let _ = path;
+        false
+    }
+}
+
+#[cfg(feature = "async")]
+impl Scanner {
+    /// Walks `root` up to `depth` levels deep on a background thread, yielding the groups of
+    /// each directory once all of its entries have been read, after any groups already added.
+    ///
+    /// The returned stream works with any async runtime. Dropping it stops the walk.
+    pub fn scan_async(mut self, root: impl Into<PathBuf>, depth: usize) -> GroupStream {
+        use std::sync::Mutex;
+        let state = Arc::new(Mutex::new(StreamState::default()));
+        let shared = Arc::clone(&state);
+        // Groups of each directory are collected by a copy of the emptied scanner.
+        let groups = std::mem::take(&mut self.groups);
+        let root = root.into();
+        std::thread::spawn(move || {
+            let send = |item: std::io::Result<FileGroup>| {
+                let mut state = shared.lock().unwrap_or_else(|e| e.into_inner());
+                state.queue.push_back(item);
+                if let Some(waker) = state.waker.take() {
+                    waker.wake();
+                }
+                !state.dropped
+            };
+            for group in groups.into_values() {
+                if !send(Ok(group)) {
+                    return;
+                }
+            }
+            // With contents first, a directory's entry comes right after everything inside it.
+            let mut pending: IndexMap<PathBuf, Scanner> = IndexMap::new();
+            for entry in walkdir::WalkDir::new(root).max_depth(depth).contents_first(true) {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(err) => {
+                        if send(Err(err.into())) {
+                            continue;
+                        }
+                        return;
+                    }
+                };
+                if entry.depth() > 0 {
+                    let parent = entry.path().parent().map(Path::to_owned).unwrap_or_default();
+                    let scanner = pending.entry(parent).or_insert_with(|| self.clone());
+                    scanner.add_entry(entry.path(), entry.depth(), entry.file_type().is_dir(), || entry.metadata().ok().map(Meta::from));
+                }
+                let done = entry.file_type().is_dir().then(|| pending.shift_remove(entry.path())).flatten();
+                for group in done.into_iter().flat_map(Scanner::into_groups) {
+                    if !send(Ok(group)) {
+                        return;
+                    }
+                }
+            }
+            for group in pending.into_values().flat_map(Scanner::into_groups) {
+                if !send(Ok(group)) {
+                    return;
+                }
+            }
+            let mut state = shared.lock().unwrap_or_else(|e| e.into_inner());
+            state.finished = true;
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+        GroupStream { state }
+    }
+}
+
+/// State shared between a `GroupStream` and the thread walking the directories.
+#[cfg(feature = "async")]
+#[derive(Default)]
+struct StreamState {
+    queue: std::collections::VecDeque<std::io::Result<FileGroup>>,
+    waker: Option<std::task::Waker>,
+    finished: bool,
+    dropped: bool,
+}
+
+/// Groups found by `Scanner::scan_async`, in the order their directories are completed.
+#[cfg(feature = "async")]
+pub struct GroupStream {
+    state: std::sync::Arc<std::sync::Mutex<StreamState>>,
+}
+
+#[cfg(feature = "async")]
+impl GroupStream {
+    /// Waits for the next group, or an error reading an entry, returning `None` once the walk is
+    /// done.
+    pub fn next_group(&mut self) -> impl std::future::Future<Output = Option<std::io::Result<FileGroup>>> + '_ {
+        use futures_core::Stream;
+        std::future::poll_fn(|cx| std::pin::Pin::new(&mut *self).poll_next(cx))
+    }
+}
+
+#[cfg(feature = "async")]
+impl futures_core::Stream for GroupStream {
+    type Item = std::io::Result<FileGroup>;
+
+    fn poll_next(self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Option<Self::Item>> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match state.queue.pop_front() {
+            Some(item) => std::task::Poll::Ready(Some(item)),
+            None if state.finished => std::task::Poll::Ready(None),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                std::task::Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl Drop for GroupStream {
+    fn drop(&mut self) {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).dropped = true;
+    }
+}
+
+/// Converts a slice of encoded file name bytes (e.g. a regex capture) back into an `OsString`.
+pub fn os_string_from_bytes(bytes: &[u8]) -> OsString {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        OsStr::from_bytes(bytes).to_owned()
+    }
+    #[cfg(not(unix))]
+    {
+        OsString::from(String::from_utf8_lossy(bytes).into_owned())
+    }
+}
+
+/// Renders an `OsStr` for display, escaping bytes that are not valid UTF-8 as `\xNN`.
+pub fn escape_os_str(s: &OsStr) -> String {
+    let mut out = String::new();
+    for chunk in s.as_encoded_bytes().utf8_chunks() {
+        out.push_str(chunk.valid());
+        for b in chunk.invalid() {
+            out.push_str(&format!("\\x{:02X}", b));
+        }
+    }
+    out
+}
+
+/// How names are rendered for display.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum QuotingStyle {
+    /// Print names as they are, escaping only bytes that are not valid UTF-8.
+    #[default]
+    Literal,
+    /// Backslash escape spaces, backslashes and nonprinting characters.
+    Escape,
+    /// Like `Escape`, but enclose names in double quotes instead of escaping spaces.
+    C,
+}
+
+/// Renders an `OsStr` for display using the given quoting style.
+///
+/// Nonprinting characters and bytes that are not valid UTF-8 are written as C style escapes in
+/// the `Escape` and `C` styles, so the output never spans more than one line.
+pub fn quote_os_str(s: &OsStr, style: QuotingStyle) -> String {
+    if style == QuotingStyle::Literal {
+        return escape_os_str(s);
+    }
+    let mut out = String::new();
+    if style == QuotingStyle::C {
+        out.push('"');
+    }
+    for chunk in s.as_encoded_bytes().utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '\\' => out.push_str("\\\\"),
+                '"' if style == QuotingStyle::C => out.push_str("\\\""),
+                ' ' if style == QuotingStyle::Escape => out.push_str("\\ "),
+                '\x07' => out.push_str("\\a"),
+                '\x08' => out.push_str("\\b"),
+                '\t' => out.push_str("\\t"),
+                '\n' => out.push_str("\\n"),
+                '\x0B' => out.push_str("\\v"),
+                '\x0C' => out.push_str("\\f"),
+                '\r' => out.push_str("\\r"),
+                c if c.is_control() => {
+                    for b in c.encode_utf8(&mut [0; 4]).bytes() {
+                        out.push_str(&format!("\\{:03o}", b));
+                    }
+                }
+                c => out.push(c),
+            }
+        }
+        for b in chunk.invalid() {
+            out.push_str(&format!("\\{:03o}", b));
+        }
+    }
+    if style == QuotingStyle::C {
+        out.push('"');
+    }
+    out
+}
+
+/// Builds a `file://` URL for the given absolute path, percent-encoding any bytes outside the
+/// unreserved set.
+pub fn file_url(path: &Path) -> String {
+    let mut url = String::from("file://");
+    for &b in path.as_os_str().as_encoded_bytes() {
+        if b.is_ascii_alphanumeric() || b"/-._~".contains(&b) {
+            url.push(b as char);
+        } else {
+            url.push_str(&format!("%{:02X}", b));
+        }
+    }
+    url
+}
+
+/// Wraps `text` in an OSC 8 escape sequence linking to `url`.
+pub fn hyperlink(url: &str, text: &str) -> String {
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+}
+
+/// Lexically normalizes a path by removing `.` components and resolving `..` against the
+/// preceding component where possible.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    use std::path::Component;
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            c => normalized.push(c),
+        }
+    }
+    if normalized.as_os_str().is_empty() {
+        normalized.push(".");
+    }
+    normalized
+}
+
+/// Path to `path` from the directory `base`, where both are normalized and either both absolute
+/// or both relative to the same directory.
+pub fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let mut path_components = path.components().peekable();
+    let mut base_components = base.components().filter(|c| *c != std::path::Component::CurDir).peekable();
+    while path_components.peek().is_some() && path_components.peek() == base_components.peek() {
+        path_components.next();
+        base_components.next();
+    }
+    let mut relative: PathBuf = base_components.map(|_| "..").collect();
+    relative.extend(path_components.filter(|c| *c != std::path::Component::CurDir));
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+    relative
+}
+
+/// Number of characters shown when printing `s`, ignoring ANSI escape sequences.
+pub fn display_width(s: &str) -> usize {
+    let mut width = 0;
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            width += 1;
+            continue;
+        }
+        match chars.next() {
+            // CSI sequences end with a letter.
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+            // OSC sequences end with ST (`ESC \`) or BEL.
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    width
+}
+
+/// Shortens `s` to at most `max` characters by replacing the middle of everything before the last
+/// `tail` characters with `…`. At least one leading character is kept, so the result may still
+/// be longer than `max`.
+pub fn truncate_middle(s: &str, tail: usize, max: usize) -> String {
+    let chars: Vec<char> = s.chars().collect();
+    if chars.len() <= max || tail >= chars.len() {
+        return s.to_string();
+    }
+    let head = &chars[..chars.len() - tail];
+    let keep = max.saturating_sub(tail + 1).clamp(1, head.len());
+    if keep >= head.len() {
+        return s.to_string();
+    }
+    let front = keep.div_ceil(2);
+    let back = keep - front;
+    let mut truncated: String = head[..front].iter().collect();
+    truncated.push('…');
+    truncated.extend(&head[head.len() - back..]);
+    truncated.extend(&chars[chars.len() - tail..]);
+    truncated
+}
+
+/// How the number of a sequence is written in its pattern.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PatternStyle {
+    /// A single `#`, or one `#` per digit for groups split by padding, as in `name.#.ext`.
+    #[default]
+    Lsn,
+    /// One `#` per padded digit, as in `name.####.ext`.
+    Hash,
+    /// A printf conversion, as in `name.%04d.ext`.
+    Printf,
+    /// A Houdini frame variable, as in `name.$F4.ext`.
+    Houdini,
+}
+
+impl FromStr for PatternStyle {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lsn" => Ok(PatternStyle::Lsn),
+            "hash" => Ok(PatternStyle::Hash),
+            "printf" => Ok(PatternStyle::Printf),
+            "houdini" => Ok(PatternStyle::Houdini),
+            _ => Err(format!("invalid pattern style '{}', expected one of lsn, hash, printf, houdini", s)),
+        }
+    }
+}
+
+/// Placeholder for numbers zero padded to `pad` digits, or unpadded if `pad` is 0, written in the
+/// given style. `conversion` is the printf conversion, `d` for decimal or `x` or `X` for hex.
+///
+/// `PatternStyle::Lsn` is written like `PatternStyle::Hash`.
+pub fn number_placeholder(style: PatternStyle, pad: usize, conversion: char) -> String {
+    match (style, pad) {
+        (PatternStyle::Lsn | PatternStyle::Hash, _) => "#".repeat(pad.max(1)),
+        (PatternStyle::Printf, 0) => format!("%{}", conversion),
+        (PatternStyle::Printf, _) => format!("%0{}{}", pad, conversion),
+        (PatternStyle::Houdini, 0) => "$F".to_string(),
+        (PatternStyle::Houdini, _) => format!("$F{}", pad),
+    }
+}
+
+/// Most separate runs of consecutive frames a frame set parsed from text may hold. A range of
+/// consecutive frames is a single run whatever its length, but each frame of a stepped range such
+/// as `1-1000x2` is a run of its own.
+pub const MAX_FRAME_RUNS: usize = 1_000_000;
+
+/// A set of frame numbers written as comma separated frames and ranges, e.g. `1-100`, `1-100x2`,
+/// `1,5,10-20` or `-5-5`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FrameSet {
+    /// Inclusive `(start, end)` runs of consecutive frames, in increasing order with gaps between.
+    runs: Vec<(isize, isize)>,
+}
+
+impl FrameSet {
+    /// Frames of inclusive `(start, end, step)` ranges, each with `start <= end` and `step > 0`.
+    fn from_ranges(ranges: impl IntoIterator<Item = (isize, isize, usize)>) -> Result<FrameSet, String> {
+        let mut runs = Vec::new();
+        for (start, end, step) in ranges {
+            if step == 1 {
+                runs.push((start, end));
+            } else {
+                let count = end.abs_diff(start) / step + 1;
+                if count > MAX_FRAME_RUNS - runs.len().min(MAX_FRAME_RUNS) {
+                    return Err(format!("too many separate frames, at most {} are supported", MAX_FRAME_RUNS));
+                }
+                let step = isize::try_from(step).unwrap_or(isize::MAX);
+                let mut frame = Some(start);
+                while let Some(n) = frame.filter(|&n| n <= end) {
+                    runs.push((n, n));
+                    frame = n.checked_add(step);
+                }
+            }
+            if runs.len() > MAX_FRAME_RUNS {
+                return Err(format!("too many separate frames, at most {} are supported", MAX_FRAME_RUNS));
+            }
+        }
+        Ok(FrameSet { runs: merge_ranges(runs) })
+    }
+    pub fn contains(&self, frame: isize) -> bool {
+        let i = self.runs.partition_point(|&(_, end)| end < frame);
+        self.runs.get(i).is_some_and(|&(start, _)| start <= frame)
+    }
+    /// Number of frames in the set, saturating at `usize::MAX`.
+    pub fn len(&self) -> usize {
+        self.runs.iter().fold(0usize, |len, &(start, end)| len.saturating_add(end.abs_diff(start).saturating_add(1)))
+    }
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+    /// Iterates over the frames in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = isize> + '_ {
+        self.runs.iter().flat_map(|&(start, end)| start..=end)
+    }
+    /// Inclusive `(start, end)` runs of consecutive frames in increasing order.
+    pub fn runs(&self) -> &[(isize, isize)] {
+        &self.runs
+    }
+    /// Frames in either set.
+    pub fn union(&self, other: &FrameSet) -> FrameSet {
+        FrameSet { runs: merge_ranges(self.runs.iter().chain(other.runs.iter()).copied()) }
+    }
+    /// Frames in both sets.
+    pub fn intersection(&self, other: &FrameSet) -> FrameSet {
+        let mut runs = Vec::new();
+        let (mut ours, mut theirs) = (self.runs.iter().peekable(), other.runs.iter().peekable());
+        while let (Some(&&(a_start, a_end)), Some(&&(b_start, b_end))) = (ours.peek(), theirs.peek()) {
+            let (start, end) = (a_start.max(b_start), a_end.min(b_end));
+            if start <= end {
+                runs.push((start, end));
+            }
+            if a_end < b_end {
+                ours.next();
+            } else {
+                theirs.next();
+            }
+        }
+        FrameSet { runs }
+    }
+    /// Frames in this set but not the other.
+    pub fn difference(&self, other: &FrameSet) -> FrameSet {
+        let mut runs = Vec::new();
+        let mut cuts = other.runs.iter().peekable();
+        for &(start, end) in &self.runs {
+            // Start of the part of this run not yet cut or kept.
+            let mut rest = Some(start);
+            while let (Some(from), Some(&&(cut_start, cut_end))) = (rest, cuts.peek()) {
+                if cut_start > end {
+                    break;
+                }
+                if cut_end < from {
+                    cuts.next();
+                    continue;
+                }
+                if cut_start > from {
+                    runs.push((from, cut_start - 1));
+                }
+                rest = cut_end.checked_add(1).filter(|&n| n <= end);
+                if cut_end <= end {
+                    cuts.next();
+                }
+            }
+            if let Some(from) = rest {
+                runs.push((from, end));
+            }
+        }
+        FrameSet { runs }
+    }
+}
+
+impl FromIterator<isize> for FrameSet {
+    fn from_iter<I: IntoIterator<Item = isize>>(iter: I) -> Self {
+        FrameSet { runs: merge_ranges(iter.into_iter().map(|n| (n, n))) }
+    }
+}
+
+impl FromStr for FrameSet {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |n: &str| n.trim().parse::<isize>().map_err(|_| format!("invalid frame '{}' in '{}'", n, s));
+        let mut ranges = Vec::new();
+        for token in s.split(',').filter(|t| !t.trim().is_empty()) {
+            let (range, step) = match token.split_once('x') {
+                Some((range, step)) => (range.trim(), step.trim().parse::<usize>().map_err(|_| format!("invalid step '{}' in '{}'", step, s))?),
+                None => (token.trim(), 1),
+            };
+            if step == 0 {
+                return Err(format!("invalid step of zero in '{}'", s));
+            }
+            // The ends are split at a `-` after the first character, so either may be negative.
+            let (start, end) = match range.get(1..).and_then(|rest| rest.find('-')) {
+                Some(i) => (parse(&range[..i + 1])?, parse(&range[i + 2..])?),
+                None => (parse(range)?, parse(range)?),
+            };
+            if start > end {
+                return Err(format!("invalid range '{}', start is past the end", token));
+            }
+            ranges.push((start, end, step));
+        }
+        let frames = FrameSet::from_ranges(ranges)?;
+        if frames.is_empty() {
+            return Err("empty frame set".to_string());
+        }
+        Ok(frames)
+    }
+}
+
+impl fmt::Display for FrameSet {
+    /// Writes the set compactly, as comma separated frames and ranges of consecutive frames.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, &(start, end)) in self.runs.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            if start == end {
+                write!(f, "{}", start)?;
+            } else {
+                write!(f, "{}-{}", start, end)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Merges inclusive `(start, end)` ranges into runs in increasing order, joining those that
+/// overlap or touch.
+pub fn merge_ranges(ranges: impl IntoIterator<Item = (isize, isize)>) -> Vec<(isize, isize)> {
+    let mut ranges: Vec<_> = ranges.into_iter().collect();
+    ranges.sort_unstable();
+    let mut runs: Vec<(isize, isize)> = Vec::new();
+    for (start, end) in ranges {
+        match runs.last_mut() {
+            Some((_, last)) if start <= last.saturating_add(1) => *last = (*last).max(end),
+            _ => runs.push((start, end)),
+        }
+    }
+    runs
+}
+
+/// Ranges of numbers missing between the smallest and largest of `numbers`, as inclusive
+/// `(start, end)` pairs in increasing order.
+pub fn gaps(numbers: impl IntoIterator<Item = isize>) -> Vec<(isize, isize)> {
+    let numbers: BTreeSet<isize> = numbers.into_iter().collect();
+    numbers
+        .iter()
+        .zip(numbers.iter().skip(1))
+        .filter(|&(&a, &b)| b - a > 1)
+        .map(|(&a, &b)| (a + 1, b - 1))
+        .collect()
+}
+
+/// Splits a path whose file name has a brace expression of numbers, such as `frame{1..100}.exr`,
+/// `frame{0001..0100..2}.exr` or `frame{1,5,10..12}.exr`, into a sequence spec with `#` in place
+/// of the braces and the frames they expand to. Ranges may run in either direction and are
+/// built like those of a `FrameSet`, so a stepped range is limited to `MAX_FRAME_RUNS` frames.
+///
+/// Returns `None` if the file name has no such brace expression, or one over that limit.
+pub fn parse_brace_spec(path: &str) -> Option<(String, FrameSet)> {
+    let name_start = path.rfind('/').map_or(0, |i| i + 1);
+    let open = name_start + path[name_start..].rfind('{')?;
+    let close = open + path[open..].find('}')?;
+    let parse = |n: &str| n.parse::<isize>().ok();
+    let mut ranges = Vec::new();
+    for item in path[open + 1..close].split(',') {
+        let parts: Vec<_> = item.split("..").collect();
+        let (start, end, step) = match parts[..] {
+            [n] => (parse(n)?, parse(n)?, 1),
+            [start, end] => (parse(start)?, parse(end)?, 1),
+            [start, end, step] => (parse(start)?, parse(end)?, step.parse::<usize>().ok().filter(|&s| s > 0)?),
+            _ => return None,
+        };
+        ranges.push((start.min(end), start.max(end), step));
+    }
+    let frames = FrameSet::from_ranges(ranges).ok()?;
+    Some((format!("{}#{}", &path[..open], &path[close + 1..]), frames))
+}
+
+/// A sequence read back from a line of lsn's output, such as `render/frame.####.exr (1..250)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ListedSequence {
+    /// Path up to the number.
+    pub prefix: String,
+    /// Rest of the path after the number.
+    pub suffix: String,
+    /// Zero padding width of the numbers, or 0 if they are unpadded.
+    pub pad: usize,
+    pub frames: FrameSet,
+}
+
+impl ListedSequence {
+    /// Parses a line printed for a sequence, whose name has a placeholder in any pattern style
+    /// and is followed by its range, e.g. `frame.%04d.exr (1..250, 250 frames @ 24fps = 10.4s)`.
+    ///
+    /// Only the range is listed, so the frames are all those within it. A single `#` is taken as
+    /// unpadded since the `lsn` style doesn't show padding. Spaces aligning the range after the
+    /// name are ignored. Returns `None` for other lines, such as those of single files.
+    pub fn parse(line: &str) -> Option<ListedSequence> {
+        line.match_indices(" (").find_map(|(pos, _)| {
+            let rest = &line[pos + 2..];
+            let (first, last) = rest[..rest.find([',', ')'])?].split_once("..")?;
+            let (first, last) = (first.parse::<isize>().ok()?, last.parse::<isize>().ok()?);
+            let listed = line[..pos].trim_end();
+            let name = listed.trim_end_matches('/');
+            let name_start = name.rfind('/').map_or(0, |i| i + 1);
+            let (start, len, pad) = name[name_start..]
+                .char_indices()
+                .rev()
+                .filter(|&(i, _)| !name[name_start..][..i].ends_with('#'))
+                .find_map(|(i, _)| placeholder_at(&name[name_start + i..]).map(|(len, pad)| (name_start + i, len, pad)))?;
+            Some(ListedSequence {
+                prefix: name[..start].to_string(),
+                suffix: listed[start + len..].to_string(),
+                pad,
+                frames: FrameSet { runs: vec![(first.min(last), first.max(last))] },
+            })
+        })
+    }
+
+    /// Path of the given frame, whose digits are padded after any minus sign.
+    pub fn path(&self, frame: isize) -> String {
+        let sign = if frame < 0 { "-" } else { "" };
+        format!("{}{}{:0pad$}{}", self.prefix, sign, frame.unsigned_abs(), self.suffix, pad = self.pad)
+    }
+}
+
+/// Length and padding of a number placeholder at the start of `s`: a run of `#`, a printf
+/// conversion like `%04d` or a Houdini variable like `$F4`.
+fn placeholder_at(s: &str) -> Option<(usize, usize)> {
+    let digits = |s: &str| s.bytes().take_while(u8::is_ascii_digit).count();
+    if let Some(rest) = s.strip_prefix("$F") {
+        let n = digits(rest);
+        return Some((2 + n, rest[..n].parse().unwrap_or(0)));
+    }
+    if let Some(rest) = s.strip_prefix('%') {
+        let n = digits(rest);
+        return rest[n..].starts_with('d').then(|| (2 + n, rest[..n].parse().unwrap_or(0)));
+    }
+    let hashes = s.bytes().take_while(|&b| b == b'#').count();
+    (hashes > 0).then_some((hashes, if hashes > 1 { hashes } else { 0 }))
+}
+
+/// Logical first and last numbers of a counter that wraps around at `modulus`, such as
+/// `9998, 9999, 0, 1` with a modulus of 10000, which gives `(9998, 1)`.
+///
+/// The span starts after the largest gap between consecutive numbers, where the gap across the
+/// wrap point counts too. Returns `None` if there are no numbers.
+pub fn wrapped_span(numbers: impl IntoIterator<Item = isize>, modulus: isize) -> Option<(isize, isize)> {
+    let numbers: Vec<isize> = numbers.into_iter().collect::<BTreeSet<_>>().into_iter().collect();
+    let (&first, &last) = (numbers.first()?, numbers.last()?);
+    let largest = numbers.windows(2).max_by_key(|pair| pair[1] - pair[0]);
+    match largest {
+        Some(pair) if pair[1] - pair[0] > first + modulus - last => Some((pair[1], pair[0])),
+        _ => Some((first, last)),
+    }
+}
+
+/// A single rename within a `RenamePlan`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rename {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// An ordering of renames in which no file is overwritten before it has been moved out of the way.
+///
+/// Cycles, such as swapping two names, are broken by first moving one file to a temporary name.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RenamePlan {
+    steps: Vec<Rename>,
+    /// Number of files renamed.
+    len: usize,
+}
+
+impl RenamePlan {
+    /// Plans renaming each source path to its target. Renames to the same path are dropped.
+    pub fn new(renames: impl IntoIterator<Item = (PathBuf, PathBuf)>) -> Result<RenamePlan, String> {
+        let renames: Vec<_> = renames.into_iter().filter(|(from, to)| from != to).collect();
+        let mut sources = HashMap::new();
+        let mut targets = HashMap::new();
+        for (i, (from, to)) in renames.iter().enumerate() {
+            if sources.insert(from.as_path(), i).is_some() {
+                return Err(format!("'{}' is renamed more than once", from.display()));
+            }
+            if let Some(j) = targets.insert(to.as_path(), i) {
+                return Err(format!(
+                    "both '{}' and '{}' would be renamed to '{}'",
+                    renames[j].0.display(),
+                    from.display(),
+                    to.display()
+                ));
+            }
+        }
+        // The rename that must happen before rename `i` because it moves the file at i's target.
+        let next: Vec<_> = renames.iter().map(|(_, to)| sources.get(to.as_path()).copied()).collect();
+        let mut done = vec![false; renames.len()];
+        let mut on_path = vec![false; renames.len()];
+        let mut temps = HashSet::new();
+        let mut steps = Vec::with_capacity(renames.len());
+        for start in 0..renames.len() {
+            if done[start] {
+                continue;
+            }
+            // Follow the chain of renames blocking each other until reaching a free target or
+            // looping back.
+            let mut path = vec![start];
+            on_path[start] = true;
+            let mut cycle = None;
+            while let Some(i) = next[*path.last().unwrap()].filter(|&i| !done[i]) {
+                if on_path[i] {
+                    cycle = path.iter().position(|&j| j == i);
+                    break;
+                }
+                on_path[i] = true;
+                path.push(i);
+            }
+            let mut temp = None;
+            if let Some(c) = cycle {
+                let tmp = temp_path(&renames[path[c]].0, &sources, &targets, &mut temps);
+                steps.push(Rename { from: renames[path[c]].0.clone(), to: tmp.clone() });
+                temp = Some((path[c], tmp));
+            }
+            for &i in path.iter().rev() {
+                let from = match &temp {
+                    Some((j, tmp)) if *j == i => tmp.clone(),
+                    _ => renames[i].0.clone(),
+                };
+                steps.push(Rename { from, to: renames[i].1.clone() });
+                done[i] = true;
+                on_path[i] = false;
+            }
+        }
+        Ok(RenamePlan { steps, len: renames.len() })
+    }
+
+    pub fn steps(&self) -> &[Rename] {
+        &self.steps
+    }
+
+    /// Number of files renamed, not counting moves to temporary names.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Targets that already exist and are not moved away by the plan itself.
+    pub fn collisions(&self) -> Vec<&Path> {
+        let sources: HashSet<_> = self.steps.iter().map(|step| step.from.as_path()).collect();
+        self.steps
+            .iter()
+            .map(|step| step.to.as_path())
+            .filter(|to| !sources.contains(to) && to.symlink_metadata().is_ok())
+            .collect()
+    }
+
+    /// Performs the renames in order, stopping at the first failure.
+    pub fn execute(&self) -> Result<(), String> {
+        for step in self.steps.iter() {
+            std::fs::rename(&step.from, &step.to).map_err(|e| {
+                format!("failed to rename '{}' to '{}': {}", step.from.display(), step.to.display(), e)
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for RenamePlan {
+    /// Writes one `from -> to` line per step.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in self.steps.iter() {
+            writeln!(f, "{} -> {}", step.from.display(), step.to.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Picks an unused temporary name next to `path`.
+fn temp_path(
+    path: &Path,
+    sources: &HashMap<&Path, usize>,
+    targets: &HashMap<&Path, usize>,
+    temps: &mut HashSet<PathBuf>,
+) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(".lsn-tmp");
+    let mut tmp = path.with_file_name(&name);
+    let mut n = 1;
+    while sources.contains_key(tmp.as_path())
+        || targets.contains_key(tmp.as_path())
+        || temps.contains(&tmp)
+        || tmp.symlink_metadata().is_ok()
+    {
+        let mut numbered = name.clone();
+        numbered.push(n.to_string());
+        tmp = path.with_file_name(numbered);
+        n += 1;
+    }
+    temps.insert(tmp.clone());
+    tmp
+}
+
+/// Whether a matched stem ends in a version token prefix, i.e. a `v` or `V` that starts the name or
+/// follows a non-alphanumeric separator (as in `comp_v` or `shot.V`).
+pub fn is_version_stem(stem: &[u8]) -> bool {
+    match stem {
+        [.., b'v' | b'V'] => stem.len() == 1 || !stem[stem.len() - 2].is_ascii_alphanumeric(),
+        _ => false,
+    }
+}
+
+/// Whether `n` is a valid UDIM tile number.
+pub fn is_udim(n: usize) -> bool {
+    (1001..=1999).contains(&n)
+}
+
+/// Returns the UDIM tiles missing from the bounding rectangle of the given tiles in UV space.
+pub fn missing_udims(tiles: &[usize]) -> Vec<usize> {
+    let uv = |t: usize| ((t - 1001) % 10, (t - 1001) / 10);
+    let tiles: Vec<_> = tiles.iter().copied().filter(|&t| is_udim(t)).collect();
+    let Some(u_min) = tiles.iter().map(|&t| uv(t).0).min() else {
+        return Vec::new();
+    };
+    let u_max = tiles.iter().map(|&t| uv(t).0).max().unwrap();
+    let v_min = tiles.iter().map(|&t| uv(t).1).min().unwrap();
+    let v_max = tiles.iter().map(|&t| uv(t).1).max().unwrap();
+    let mut missing = Vec::new();
+    for v in v_min..=v_max {
+        for u in u_min..=u_max {
+            let tile = 1001 + u + 10 * v;
+            if !tiles.contains(&tile) {
+                missing.push(tile);
+            }
+        }
+    }
+    missing
+}
+
+/// Splits a string of command line arguments on whitespace, keeping single or double quoted
+/// text together.
+pub fn split_args(s: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut arg: Option<String> = None;
+    let mut quote = None;
+    for c in s.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => arg.get_or_insert_with(String::new).push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                arg.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => args.extend(arg.take()),
+            (None, c) => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+    args.extend(arg);
+    args
+}
+
+/// Parses a human readable size such as `512`, `10K`, `1.5MB` or `10GiB` into bytes.
+///
+/// Unit prefixes are binary (powers of 1024) to match `du` and `find -size`.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let num: f64 = num.parse().map_err(|_| format!("invalid size '{}'", s))?;
+    let exp = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" | "KIB" => 1,
+        "M" | "MB" | "MIB" => 2,
+        "G" | "GB" | "GIB" => 3,
+        "T" | "TB" | "TIB" => 4,
+        "P" | "PB" | "PIB" => 5,
+        _ => return Err(format!("invalid size unit '{}'", unit)),
+    };
+    Ok((num * 1024_f64.powi(exp)) as u64)
+}
+
+/// Formats a size in bytes for humans with binary units, e.g. `512`, `1.5K` or `40M`.
+///
+/// One decimal is shown for values below 10, as `ls -h` does.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["K", "M", "G", "T", "P"];
+    if bytes < 1024 {
+        return bytes.to_string();
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if value < 10.0 {
+        format!("{:.1}{}", value, UNITS[unit])
+    } else {
+        format!("{:.0}{}", value, UNITS[unit])
+    }
+}
+
+/// Formats a number with `,` between groups of three digits, e.g. `1,234,567`.
+pub fn format_thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// Unit in which sizes are shown.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SizeUnit {
+    /// Bytes with thousands separators, e.g. `1,234,567`.
+    Bytes,
+    /// Whole KiB, rounded up, e.g. `1,206K`.
+    Kb,
+    /// Whole MiB, rounded up.
+    Mb,
+    /// Whole GiB, rounded up.
+    Gb,
+    /// The largest binary unit that keeps the value below 1024, as `format_size` does.
+    Auto,
+}
+
+impl SizeUnit {
+    /// Formats a size in bytes in this unit.
+    pub fn format(self, bytes: u64) -> String {
+        let scaled = |shift: u32, suffix: &str| format!("{}{}", format_thousands(bytes.div_ceil(1 << shift)), suffix);
+        match self {
+            SizeUnit::Bytes => format_thousands(bytes),
+            SizeUnit::Kb => scaled(10, "K"),
+            SizeUnit::Mb => scaled(20, "M"),
+            SizeUnit::Gb => scaled(30, "G"),
+            SizeUnit::Auto => format_size(bytes),
+        }
+    }
+}
+
+impl FromStr for SizeUnit {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "bytes" => Ok(SizeUnit::Bytes),
+            "kb" => Ok(SizeUnit::Kb),
+            "mb" => Ok(SizeUnit::Mb),
+            "gb" => Ok(SizeUnit::Gb),
+            "auto" => Ok(SizeUnit::Auto),
+            _ => Err(format!("invalid size unit '{}', expected one of bytes, kb, mb, gb, auto", s)),
+        }
+    }
+}
+
+/// Summary statistics of a set of sizes.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SizeStats {
+    pub min: u64,
+    pub max: u64,
+    pub mean: f64,
+    /// Population standard deviation.
+    pub std_dev: f64,
+}
+
+impl SizeStats {
+    /// Computes the statistics of `sizes`, returning `None` if there are none.
+    pub fn new(sizes: impl IntoIterator<Item = u64>) -> Option<SizeStats> {
+        let sizes: Vec<u64> = sizes.into_iter().collect();
+        let min = *sizes.iter().min()?;
+        let max = *sizes.iter().max()?;
+        let n = sizes.len() as f64;
+        let mean = sizes.iter().map(|&s| s as f64).sum::<f64>() / n;
+        let variance = sizes.iter().map(|&s| (s as f64 - mean).powi(2)).sum::<f64>() / n;
+        Some(SizeStats { min, max, mean, std_dev: variance.sqrt() })
+    }
+}
+
+impl fmt::Display for SizeStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "min {}, max {}, mean {}, sd {}",
+            format_size(self.min),
+            format_size(self.max),
+            format_size(self.mean.round() as u64),
+            format_size(self.std_dev.round() as u64)
+        )
+    }
+}
+
+/// Type of file recognized from the leading bytes of its contents.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FileKind {
+    pub mime: &'static str,
+    /// Lowercase extension usually given to files of this type, without the leading `.`.
+    pub extension: &'static str,
+}
+
+/// Formats common in image pipelines that `infer` doesn't recognize, by MIME type and extension.
+/// They are checked before those of `infer`.
+const PIPELINE_FORMATS: &[(&str, &str, infer::Matcher)] = &[
+    ("image/x-exr", "exr", |head| head.starts_with(b"\x76\x2f\x31\x01")),
+    ("image/x-dpx", "dpx", |head| head.starts_with(b"SDPX") || head.starts_with(b"XPDS")),
+    ("image/x-cineon", "cin", |head| head.starts_with(b"\x80\x2a\x5f\xd7")),
+    ("image/vnd.radiance", "hdr", |head| head.starts_with(b"#?RADIANCE") || head.starts_with(b"#?RGBE")),
+    ("image/vnd.ms-dds", "dds", |head| head.starts_with(b"DDS ")),
+    ("image/x-sgi", "rgb", |head| head.starts_with(b"\x01\xda")),
+    ("application/x-hdf5", "h5", |head| head.starts_with(b"\x89HDF\r\n\x1a\n")),
+    ("application/x-alembic", "abc", |head| head.starts_with(b"Ogawa")),
+    ("application/x-openvdb", "vdb", |head| head.starts_with(b"\x20\x42\x44\x56")),
+];
+
+/// Other extensions given to files of a type, by the extension `FileKind` reports for it.
+const EXTENSION_ALIASES: &[(&str, &[&str])] = &[
+    ("jpg", &["jpeg", "jpe"]),
+    ("tif", &["tiff", "tx", "tex"]),
+    ("hdr", &["pic"]),
+    ("rgb", &["sgi", "rgba", "bw"]),
+    ("h5", &["hdf5"]),
+    ("mov", &["qt"]),
+    ("mp4", &["m4v", "m4a", "mov", "3gp"]),
+    ("gz", &["tgz"]),
+    ("ogg", &["oga"]),
+];
+
+/// Number of leading bytes of a file needed to recognize its type.
+pub const MAGIC_LEN: usize = 8192;
+
+impl FileKind {
+    /// Recognizes the type of a file from its first bytes.
+    pub fn detect(head: &[u8]) -> Option<FileKind> {
+        static INFER: std::sync::OnceLock<infer::Infer> = std::sync::OnceLock::new();
+        let infer = INFER.get_or_init(|| {
+            let mut infer = infer::Infer::new();
+            for &(mime, extension, matcher) in PIPELINE_FORMATS {
+                infer.add(mime, extension, matcher);
+            }
+            infer
+        });
+        infer.get(head).map(|kind| FileKind { mime: kind.mime_type(), extension: kind.extension() })
+    }
+
+    /// Whether files with the extension `ext` (with or without the leading `.`) are usually of
+    /// a type that can be recognized, ignoring case.
+    pub fn is_known_extension(ext: &str) -> bool {
+        let ext = ext.trim_start_matches('.').to_ascii_lowercase();
+        PIPELINE_FORMATS.iter().any(|&(_, extension, _)| extension == ext)
+            || EXTENSION_ALIASES.iter().any(|(_, aliases)| aliases.contains(&ext.as_str()))
+            || infer::is_supported(&ext)
+    }
+
+    /// Whether `ext` (with or without the leading `.`) is usual for this type, ignoring case.
+    pub fn has_extension(&self, ext: &str) -> bool {
+        let ext = ext.trim_start_matches('.');
+        let aliases = EXTENSION_ALIASES.iter().find(|&&(extension, _)| extension == self.extension).map_or(&[][..], |&(_, aliases)| aliases);
+        std::iter::once(&self.extension).chain(aliases).any(|e| e.eq_ignore_ascii_case(ext))
+    }
+}
+
+/// Error for durations that don't fit in a `Duration` or reach back before the epoch.
+const DURATION_OUT_OF_RANGE: &str = "duration out of range";
+
+/// Parses a duration such as `90s`, `30m`, `1h30m`, `2d` or `1w`.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let mut total = 0u64;
+    let mut rest = s.trim();
+    if rest.is_empty() {
+        return Err("empty duration".to_string());
+    }
+    while !rest.is_empty() {
+        let split = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        if split == 0 {
+            return Err(format!("invalid duration '{}'", s));
+        }
+        let num: u64 = rest[..split].parse().map_err(|_| format!("invalid duration '{}'", s))?;
+        rest = &rest[split..];
+        let unit_len = rest.find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len());
+        let secs = match &rest[..unit_len] {
+            "s" | "" => 1,
+            "m" | "min" => 60,
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            "w" => 7 * 24 * 60 * 60,
+            unit => return Err(format!("invalid duration unit '{}'", unit)),
+        };
+        rest = &rest[unit_len..];
+        total = num.checked_mul(secs).and_then(|secs| total.checked_add(secs)).ok_or(DURATION_OUT_OF_RANGE)?;
+    }
+    Ok(Duration::from_secs(total))
+}
+
+/// Formats the running time of `frames` frames at `fps` frames per second, e.g. `10.4s` or
+/// `1h 2m 5.0s`.
+pub fn format_frame_time(frames: usize, fps: f64) -> String {
+    let tenths = (frames as f64 / fps * 10.0).round() as u64;
+    let (hours, minutes, seconds) = (tenths / 36000, tenths / 600 % 60, (tenths % 600) as f64 / 10.0);
+    if hours > 0 {
+        format!("{}h {}m {:.1}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {:.1}s", minutes, seconds)
+    } else {
+        format!("{:.1}s", seconds)
+    }
+}
+
+/// Parses either a duration relative to `now` (e.g. `1h`), or a local date (`2024-01-05`),
+/// date and time (`2024-01-05 13:00`) or RFC 3339 timestamp into a point in time.
+pub fn parse_time_spec(s: &str, now: SystemTime) -> Result<SystemTime, String> {
+    use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+    match parse_duration(s) {
+        Ok(duration) => return now.checked_sub(duration).ok_or_else(|| DURATION_OUT_OF_RANGE.to_string()),
+        Err(err) if err == DURATION_OUT_OF_RANGE => return Err(err),
+        Err(_) => {}
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Ok(time.into());
+    }
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(s, fmt).ok())
+        .or_else(|| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok().and_then(|d| d.and_hms_opt(0, 0, 0)));
+    naive
+        .and_then(|naive| Local.from_local_datetime(&naive).earliest())
+        .map(SystemTime::from)
+        .ok_or_else(|| format!("invalid duration or date '{}'", s))
 }
 
 #[cfg(test)]
@@ -12,80 +2114,672 @@ mod tests {
     #[test]
     fn digit_in_extension() {
         let regex = build_regex();
-        let s = "test2.3dv";
+        let s = b"test2.3dv";
         let caps = regex.captures(s).unwrap();
-        assert_eq!("test", &caps["stem"]);
-        assert_eq!("2", &caps["num"]);
-        assert_eq!(".3dv", &caps["ext"]);
+        assert_eq!(b"test", &caps["stem"]);
+        assert_eq!(b"2", &caps["num"]);
+        assert_eq!(b".3dv", &caps["ext"]);
     }
 
     #[test]
     fn basic() {
         let regex = build_regex();
-        let s = "test2.txt";
+        let s = b"test2.txt";
         let caps = regex.captures(s).unwrap();
-        assert_eq!("test", &caps["stem"]);
-        assert_eq!("2", &caps["num"]);
-        assert_eq!(".txt", &caps["ext"]);
+        assert_eq!(b"test", &caps["stem"]);
+        assert_eq!(b"2", &caps["num"]);
+        assert_eq!(b".txt", &caps["ext"]);
     }
 
     #[test]
     fn many_digits() {
         let regex = build_regex();
-        let s = "some1other5test2.3dv";
+        let s = b"some1other5test2.3dv";
         let caps = regex.captures(s).unwrap();
-        assert_eq!("some1other5test", &caps["stem"]);
-        assert_eq!("2", &caps["num"]);
-        assert_eq!(".3dv", &caps["ext"]);
+        assert_eq!(b"some1other5test", &caps["stem"]);
+        assert_eq!(b"2", &caps["num"]);
+        assert_eq!(b".3dv", &caps["ext"]);
     }
 
     #[test]
     fn no_extension() {
         let regex = build_regex();
-        let s = "some1other5test2";
+        let s = b"some1other5test2";
         let caps = regex.captures(s).unwrap();
-        assert_eq!("some1other5test", &caps["stem"]);
-        assert_eq!("2", &caps["num"]);
-        assert_eq!("", &caps["ext"]);
+        assert_eq!(b"some1other5test", &caps["stem"]);
+        assert_eq!(b"2", &caps["num"]);
+        assert_eq!(b"", &caps["ext"]);
     }
 
     #[test]
     fn one_period_extension() {
         let regex = build_regex();
-        let s = "some1other5test2.";
+        let s = b"some1other5test2.";
         let caps = regex.captures(s).unwrap();
-        assert_eq!("some1other5test", &caps["stem"]);
-        assert_eq!("2", &caps["num"]);
-        assert_eq!(".", &caps["ext"]);
+        assert_eq!(b"some1other5test", &caps["stem"]);
+        assert_eq!(b"2", &caps["num"]);
+        assert_eq!(b".", &caps["ext"]);
     }
 
     #[test]
     fn start_with_number() {
         let regex = build_regex();
-        let s = "0some1other5test2.t";
+        let s = b"0some1other5test2.t";
         let caps = regex.captures(s).unwrap();
-        assert_eq!("0some1other5test", &caps["stem"]);
-        assert_eq!("2", &caps["num"]);
-        assert_eq!(".t", &caps["ext"]);
+        assert_eq!(b"0some1other5test", &caps["stem"]);
+        assert_eq!(b"2", &caps["num"]);
+        assert_eq!(b".t", &caps["ext"]);
     }
 
     #[test]
     fn just_number_with_extension() {
         let regex = build_regex();
-        let s = "01.t";
+        let s = b"01.t";
         let caps = regex.captures(s).unwrap();
-        assert_eq!("", &caps["stem"]);
-        assert_eq!("01", &caps["num"]);
-        assert_eq!(".t", &caps["ext"]);
+        assert_eq!(b"", &caps["stem"]);
+        assert_eq!(b"01", &caps["num"]);
+        assert_eq!(b".t", &caps["ext"]);
     }
 
     #[test]
     fn just_number() {
         let regex = build_regex();
-        let s = "01";
+        let s = b"01";
         let caps = regex.captures(s).unwrap();
-        assert_eq!("", &caps["stem"]);
-        assert_eq!("01", &caps["num"]);
-        assert_eq!("", &caps["ext"]);
+        assert_eq!(b"", &caps["stem"]);
+        assert_eq!(b"01", &caps["num"]);
+        assert_eq!(b"", &caps["ext"]);
+    }
+
+    #[test]
+    fn non_utf8_name() {
+        let regex = build_regex();
+        let s = b"\xFFtest\xFE2.\xFDexr";
+        let caps = regex.captures(s).unwrap();
+        assert_eq!(b"\xFFtest\xFE", &caps["stem"]);
+        assert_eq!(b"2", &caps["num"]);
+        assert_eq!(b".\xFDexr", &caps["ext"]);
+    }
+
+    #[test]
+    fn non_ascii_digits_are_not_numbers() {
+        let regex = build_regex();
+        assert!(regex.captures("test٣".as_bytes()).is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn escape_invalid_bytes() {
+        let name = os_string_from_bytes(b"caf\xC3\xA9\xFF1.exr");
+        assert_eq!("café\\xFF1.exr", escape_os_str(&name));
+    }
+
+    #[test]
+    fn file_url_encoding() {
+        assert_eq!("file:///tmp/frames", file_url(Path::new("/tmp/frames")));
+        assert_eq!("file:///tmp/my%20shot/a%23b", file_url(Path::new("/tmp/my shot/a#b")));
+    }
+
+    #[test]
+    fn hyperlink_escape() {
+        assert_eq!(
+            "\x1b]8;;file:///tmp\x1b\\tmp\x1b]8;;\x1b\\",
+            hyperlink("file:///tmp", "tmp")
+        );
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(Ok(512), parse_size("512"));
+        assert_eq!(Ok(10 * 1024), parse_size("10K"));
+        assert_eq!(Ok(1536 * 1024), parse_size("1.5MB"));
+        assert_eq!(Ok(10 * 1024 * 1024 * 1024), parse_size("10GiB"));
+        assert!(parse_size("10X").is_err());
+        assert!(parse_size("GB").is_err());
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(Ok(Duration::from_secs(90)), parse_duration("90s"));
+        assert_eq!(Ok(Duration::from_secs(5400)), parse_duration("1h30m"));
+        assert_eq!(Ok(Duration::from_secs(2 * 86400)), parse_duration("2d"));
+        assert!(parse_duration("1y").is_err());
+        assert!(parse_duration("h").is_err());
+        assert_eq!(Err(DURATION_OUT_OF_RANGE.to_string()), parse_duration("99999999999999999w"));
+        assert_eq!(Err(DURATION_OUT_OF_RANGE.to_string()), parse_duration("18446744073709551615s1s"));
+    }
+
+    #[test]
+    fn time_specs() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        assert_eq!(Ok(now - Duration::from_secs(3600)), parse_time_spec("1h", now));
+        assert_eq!(
+            Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(86400)),
+            parse_time_spec("1970-01-02T00:00:00Z", now)
+        );
+        assert!(parse_time_spec("2024-01-05", now).is_ok());
+        assert!(parse_time_spec("yesterday", now).is_err());
+        assert_eq!(Err(DURATION_OUT_OF_RANGE.to_string()), parse_time_spec("99999999999999999w", now));
+        assert_eq!(Err(DURATION_OUT_OF_RANGE.to_string()), parse_time_spec("18446744073709551615s", now));
+    }
+
+    #[test]
+    fn udims() {
+        assert!(is_udim(1001));
+        assert!(!is_udim(1000));
+        assert!(!is_udim(2000));
+        assert!(missing_udims(&[1001, 1002, 1011, 1012]).is_empty());
+        assert_eq!(vec![1012], missing_udims(&[1001, 1002, 1011]));
+        assert_eq!(vec![1002], missing_udims(&[1001, 1003]));
+        assert!(missing_udims(&[]).is_empty());
+    }
+
+    #[test]
+    fn version_stems() {
+        assert!(is_version_stem(b"comp_v"));
+        assert!(is_version_stem(b"shot.V"));
+        assert!(is_version_stem(b"v"));
+        assert!(!is_version_stem(b"dev"));
+        assert!(!is_version_stem(b"comp_"));
+        assert!(!is_version_stem(b""));
+    }
+
+    #[test]
+    fn oversized_numbers() {
+        let matcher = Matcher::default();
+        let number = |name: &str| matcher.split(name.as_bytes()).and_then(|split| split.number());
+        assert_eq!(Some(MAX_NUMBER as isize), number("f999999999999999999.exr"));
+        assert_eq!(Some(7), number("f0000000000000000000000007.exr"));
+        assert_eq!(None, number("f1000000000000000000.exr"));
+        assert_eq!(None, number("id1234567890123456789012345678901234567890.dat"));
+        assert_eq!(None, Matcher::default().with_radix(Radix::Hex).split(b"fffffffffffffff0").and_then(|split| split.number()));
+        assert_eq!(None, parse_numbered_name("s9223372036854775807.exr"));
+    }
+
+    #[test]
+    fn frame_set_parse() {
+        let set: FrameSet = "1-5,8,10-14x2".parse().unwrap();
+        assert_eq!(vec![1, 2, 3, 4, 5, 8, 10, 12, 14], set.iter().collect::<Vec<_>>());
+        assert!("5-1".parse::<FrameSet>().is_err());
+        assert!("1-10x0".parse::<FrameSet>().is_err());
+        assert!("a-b".parse::<FrameSet>().is_err());
+        assert!("".parse::<FrameSet>().is_err());
+
+        let set: FrameSet = "-5-5,-12--10,20x3".parse().unwrap();
+        assert_eq!("-12--10,-5-5,20", set.to_string());
+        assert!(set.contains(-11) && set.contains(0) && !set.contains(-6) && !set.contains(6));
+        // Long ranges are kept as runs rather than expanded.
+        let set: FrameSet = "1-9223372036854775807".parse().unwrap();
+        assert_eq!(isize::MAX as usize, set.len());
+        assert!(set.contains(isize::MAX) && !set.contains(0));
+        assert!("1-18446744073709551615".parse::<FrameSet>().is_err());
+        assert!("1-9223372036854775807x2".parse::<FrameSet>().is_err());
+        assert_eq!(MAX_FRAME_RUNS, format!("1-{}x2", MAX_FRAME_RUNS * 2).parse::<FrameSet>().unwrap().len());
+    }
+
+    #[test]
+    fn frame_set_display() {
+        let set: FrameSet = [7, 1, 2, 3, 5, 9, 10].into_iter().collect();
+        assert_eq!("1-3,5,7,9-10", set.to_string());
+        assert_eq!("", FrameSet::default().to_string());
+    }
+
+    #[test]
+    fn frame_set_operations() {
+        let set = |s: &str| s.parse::<FrameSet>().unwrap();
+        assert_eq!("1-39,51-100", set("1-100").difference(&set("40-50")).to_string());
+        assert_eq!("1,3,5", set("1-6").intersection(&set("1-200x2")).to_string());
+        assert_eq!("1-5,8-9", set("1-3,8").union(&set("3-5,9")).to_string());
+        assert!(set("1-3").difference(&set("1-10")).is_empty());
+        assert_eq!("1,5-6,10", set("1-10").difference(&set("2-4,7-9,11-20")).to_string());
+        assert_eq!("-3--1,2", set("-5-2").difference(&set("-10--4,0-1")).to_string());
+        assert_eq!("3-4,8", set("1-4,6-8").intersection(&set("3-5,8-100")).to_string());
+        assert_eq!(vec![(2, 6), (9, 9)], merge_ranges([(4, 5), (2, 3), (9, 9), (6, 6), (3, 4)]));
+        assert!(merge_ranges([]).is_empty());
+        assert_eq!("1-9223372036854775807", set("1-10").union(&set("5-9223372036854775807")).to_string());
+    }
+
+    #[test]
+    fn quoting_styles() {
+        let name = OsStr::new("my shot\n\"1\".exr\x01");
+        assert_eq!("my shot\n\"1\".exr\x01", quote_os_str(name, QuotingStyle::Literal));
+        assert_eq!("my\\ shot\\n\"1\".exr\\001", quote_os_str(name, QuotingStyle::Escape));
+        assert_eq!("\"my shot\\n\\\"1\\\".exr\\001\"", quote_os_str(name, QuotingStyle::C));
+        assert_eq!("a\\\\b", quote_os_str(OsStr::new("a\\b"), QuotingStyle::Escape));
+    }
+
+    #[test]
+    fn split_arguments() {
+        assert_eq!(vec!["-l", "--sort", "size:desc"], split_args("  -l --sort\tsize:desc "));
+        assert_eq!(vec!["--time-style", "+%Y %m", ""], split_args("--time-style '+%Y %m' \"\""));
+        assert!(split_args("").is_empty());
+    }
+
+    #[test]
+    fn numbered_names() {
+        assert_eq!(
+            Some(NumberedName { stem: "frame.", number: 42, pad: 4, ext: ".exr" }),
+            parse_numbered_name("frame.0042.exr")
+        );
+        assert_eq!(
+            Some(NumberedName { stem: "test", number: 2, pad: 1, ext: ".3dv" }),
+            parse_numbered_name("test2.3dv")
+        );
+        assert_eq!(Some(NumberedName { stem: "", number: 1, pad: 2, ext: "" }), parse_numbered_name("01"));
+        assert_eq!(None, parse_numbered_name("notes.txt"));
+        assert_eq!(None, parse_numbered_name("id123456789012345678901234567890"));
+    }
+
+    #[test]
+    fn scanner_matches_regex() {
+        let regex = Matcher::from_pattern(DEFAULT_PATTERN).unwrap();
+        let names: [&[u8]; 14] = [
+            b"test2.3dv",
+            b"some1other5test2.3dv",
+            b"some1other5test2",
+            b"some1other5test2.",
+            b"0some1other5test2.t",
+            b"01.t",
+            b"01",
+            b"a1.b2.c",
+            b"frame.0001.exr",
+            b"notes.txt",
+            b"v2a",
+            b"",
+            b".5",
+            b"\xFFtest\xFE2.\xFDexr",
+        ];
+        for name in names {
+            assert_eq!(regex.split(name), Matcher::default().split(name), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn custom_pattern() {
+        assert!(Matcher::from_pattern(r"(?<stem>.*)(?<num>\d+)").is_err());
+        assert!(Matcher::from_pattern(r"(").is_err());
+        let matcher = Matcher::from_pattern(r"^(?<stem>.*?)(?<num>\d+)(?<ext>.*)$").unwrap();
+        let split = matcher.split(b"a1.b2.c").unwrap();
+        assert_eq!((&b"a"[..], &b"1"[..], &b".b2.c"[..]), (split.stem, split.num, split.ext));
+        assert_eq!(Some(1), split.number());
+    }
+
+    #[test]
+    fn placeholders() {
+        assert_eq!("####", number_placeholder(PatternStyle::Hash, 4, 'd'));
+        assert_eq!("#", number_placeholder(PatternStyle::Hash, 0, 'd'));
+        assert_eq!("%04d", number_placeholder(PatternStyle::Printf, 4, 'd'));
+        assert_eq!("%X", number_placeholder(PatternStyle::Printf, 0, 'X'));
+        assert_eq!("$F4", number_placeholder(PatternStyle::Houdini, 4, 'd'));
+        assert_eq!("$F", number_placeholder(PatternStyle::Houdini, 0, 'd'));
+        assert_eq!(Ok(PatternStyle::Houdini), "houdini".parse());
+        assert!("nuke".parse::<PatternStyle>().is_err());
+    }
+
+    #[test]
+    fn canonical_patterns() {
+        let group = |scanner: Scanner, names: &[&str]| {
+            let mut scanner = scanner;
+            scanner.extend(names.iter().map(|name| (PathBuf::from(name), None)));
+            scanner.into_groups()
+        };
+        let patterns = |grp: &FileGroup| {
+            [PatternStyle::Lsn, PatternStyle::Hash, PatternStyle::Printf, PatternStyle::Houdini].map(|style| grp.canonical_pattern(style).into_string().unwrap())
+        };
+        let padded = group(Scanner::default(), &["frame.0001.exr", "frame.0002.exr"]);
+        assert_eq!(["frame.#.exr", "frame.####.exr", "frame.%04d.exr", "frame.$F4.exr"], patterns(&padded[0]));
+        assert_eq!("frame.%04d.exr", padded[0].printf_pattern());
+        let unpadded = group(Scanner::default(), &["frame.9.exr", "frame.10.exr"]);
+        assert_eq!(["frame.#.exr", "frame.#.exr", "frame.%d.exr", "frame.$F.exr"], patterns(&unpadded[0]));
+        // Padding is that of the narrowest number, unless groups are split by it.
+        let mixed = group(Scanner::default(), &["f.001.exr", "f.2.exr"]);
+        assert_eq!("f.%01d.exr", mixed[0].printf_pattern());
+        let split = group(Scanner::default().with_split_padding(true), &["f.001.exr", "f.002.exr", "f.3.exr"]);
+        assert_eq!(["f.###.exr", "f.###.exr", "f.%03d.exr", "f.$F3.exr"], patterns(&split[0]));
+        assert_eq!(["f.#.exr", "f.#.exr", "f.%d.exr", "f.$F.exr"], patterns(&split[1]));
+        let hex = group(Scanner::new(Matcher::default().with_radix(Radix::Hex)), &["t_0A.png", "t_0B.png"]);
+        assert_eq!(["t_#.png", "t_##.png", "t_%02X.png", "t_$F2.png"], patterns(&hex[0]));
+        let dated = group(Scanner::default().with_dates(true), &["log_2024-01-01.txt", "log_2024-01-02.txt"]);
+        assert_eq!(["log_<date>.txt"; 4], patterns(&dated[0]));
+    }
+
+    #[test]
+    fn scanning_entries() {
+        let meta = |size| Some(Meta { size, ..Meta::default() });
+        let entries = vec![
+            (PathBuf::from("shots/a/frame.0001.exr"), meta(10)),
+            (PathBuf::from("shots/a/notes.txt"), None),
+            (PathBuf::from("shots/a/frame.0003.exr"), meta(20)),
+            (PathBuf::from("shots/b/frame.0001.exr"), None),
+            (PathBuf::from("shots/take2"), Some(Meta { is_dir: true, ..Meta::default() })),
+        ];
+        let groups = Scanner::from_entries(entries.into_iter()).into_groups();
+        assert_eq!(4, groups.len());
+        assert_eq!((Some(Path::new("shots/a")), OsStr::new("frame."), OsStr::new(".exr")), (groups[0].parent.as_deref(), groups[0].stem.as_os_str(), groups[0].ext.as_os_str()));
+        assert!(groups[0].is_sequence());
+        assert_eq!(Some((1, 3)), groups[0].span());
+        assert_eq!(Some(30), groups[0].size());
+        assert_eq!(OsStr::new("shots/a/frame.#.exr"), groups[0].path());
+        assert_eq!((None, None), (groups[1].span(), groups[1].size()));
+        assert_eq!(OsStr::new(".txt"), groups[1].ext);
+        assert!(!groups[2].is_sequence());
+        assert_eq!(OsStr::new("take2"), groups[3].stem);
+    }
+
+    #[test]
+    fn scanner_options() {
+        let names = |scanner: Scanner, paths: &[&str]| {
+            let mut scanner = scanner;
+            scanner.extend(paths.iter().map(|path| (PathBuf::from(path), None)));
+            scanner.into_groups().iter().map(|grp| grp.path().into_string().unwrap()).collect::<Vec<_>>()
+        };
+        let paths = ["a/.f.1.exr", "a/.f.2.exr", "a/f.1.exr", "a/f.2.exr"];
+        assert_eq!(vec!["a/f.#.exr"], names(Scanner::default(), &paths));
+        assert_eq!(vec!["a/.f.#.exr", "a/f.#.exr"], names(Scanner::default().with_hidden(true), &paths));
+        assert_eq!(vec!["a/f.1.exr", "a/f.2.exr"], names(Scanner::default().with_classic(true), &paths[2..]));
+
+        let padded = ["f.0001.exr", "f.0002.exr", "f.3.exr", "f.4.exr"];
+        assert_eq!(vec!["f.#.exr"], names(Scanner::default(), &padded));
+        assert_eq!(vec!["f.####.exr", "f.#.exr"], names(Scanner::default().with_split_padding(true), &padded));
+
+        let hex = ["f.0a.exr", "f.0b.exr"];
+        assert_eq!(vec!["f.0a.exr", "f.0b.exr"], names(Scanner::default(), &hex));
+        assert_eq!(vec!["f.#.exr"], names(Scanner::new(Matcher::default().with_radix(Radix::Hex)), &hex));
+
+        let dated = ["log_2024-01-01.txt", "log_2024-01-03.txt"];
+        assert_eq!(vec!["log_<date>.txt"], names(Scanner::default().with_dates(true), &dated));
+        let mut scanner = Scanner::default().with_group_dirs(true);
+        scanner.extend(["take1", "take2"].map(|path| (PathBuf::from(path), Some(Meta { is_dir: true, ..Meta::default() }))));
+        assert_eq!(vec![OsString::from("take#")], scanner.groups().map(FileGroup::path).collect::<Vec<_>>());
+        let mut scanner = Scanner::default().with_versions(true).with_wrap(Some(10));
+        scanner.extend(["comp_v1.nk", "comp_v2.nk", "f.9.exr", "f.0.exr"].map(|path| (PathBuf::from(path), None)));
+        let groups = scanner.into_groups();
+        assert!(groups[0].versioned);
+        assert_eq!(Some((9, 0)), groups[1].span());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn scanner_ignore_files() {
+        let root = std::env::temp_dir().join(format!("lsn-ignore-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("cache")).unwrap();
+        std::fs::write(root.join(ignore::FILE_NAME), "*.meta\ncache/\n[\n").unwrap();
+        let entries = [("f.1.exr", false), ("f.1.meta", false), ("cache", true), ("cache/f.1.exr", false)];
+        let paths = |ignore_files: bool| {
+            let mut scanner = Scanner::default().with_ignore_files(ignore_files);
+            for (name, is_dir) in entries {
+                let path = root.join(name);
+                scanner.add_entry(&path, Path::new(name).components().count(), is_dir, || None);
+            }
+            let errors = scanner.take_errors();
+            let names = scanner.groups().map(|grp| grp.path().to_string_lossy()[root.as_os_str().len() + 1..].to_string()).collect::<Vec<_>>();
+            (names, errors.len())
+        };
+        let (ignored, errors) = paths(true);
+        let (all, _) = paths(false);
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!((vec!["f.1.exr".to_string()], 1), (ignored, errors));
+        assert_eq!(vec!["f.1.exr", "f.1.meta", "cache", "cache/f.1.exr"], all);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn scanning_async() {
+        use futures_util::StreamExt;
+        let root = std::env::temp_dir().join(format!("lsn-scan-async-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("plates")).unwrap();
+        for name in ["frame.1.exr", "frame.2.exr", "plates/p1.dpx", "plates/p2.dpx"] {
+            std::fs::write(root.join(name), "x").unwrap();
+        }
+        let stream = Scanner::default().scan_async(&root, 2);
+        let groups: Vec<_> = stream.map(Result::unwrap).collect().await;
+        let mut stream = Scanner::default().scan_async(&root, 2);
+        let first = stream.next_group().await.unwrap().unwrap();
+        drop(stream);
+        std::fs::remove_dir_all(&root).unwrap();
+        // Directories are yielded after their contents.
+        assert_eq!(Some(root.join("plates").as_path()), first.parent.as_deref());
+        assert_eq!(Some(4), groups.iter().filter(|g| !g.is_dir()).map(|g| g.size()).sum());
+        let mut names: Vec<_> = groups.iter().map(|g| (g.parent.as_deref().unwrap().strip_prefix(&root).unwrap().to_owned(), g.stem.clone(), g.span())).collect();
+        names.sort();
+        assert_eq!(
+            vec![
+                (PathBuf::new(), OsString::from("frame."), Some((1, 2))),
+                (PathBuf::new(), OsString::from("plates"), None),
+                (PathBuf::from("plates"), OsString::from("p"), Some((1, 2))),
+            ],
+            names
+        );
+    }
+
+    #[test]
+    fn brace_specs() {
+        let spec = |s: &str| parse_brace_spec(s).map(|(spec, frames)| (spec, frames.to_string()));
+        assert_eq!(spec("frame{1..100}.exr"), Some(("frame#.exr".to_string(), "1-100".to_string())));
+        assert_eq!(spec("shots/a{0001..0010..3}.exr"), Some(("shots/a#.exr".to_string(), "1,4,7,10".to_string())));
+        assert_eq!(spec("f{1,5,10..12}"), Some(("f#".to_string(), "1,5,10-12".to_string())));
+        assert_eq!(spec("f{3..1}"), Some(("f#".to_string(), "1-3".to_string())));
+        assert_eq!(spec("{1..2}/f.exr"), None);
+        assert_eq!(spec("f{a,b}.exr"), None);
+        assert_eq!(spec("f{1..2..0}.exr"), None);
+        assert_eq!(spec("f{-2..2}.exr"), Some(("f#.exr".to_string(), "-2-2".to_string())));
+        assert_eq!(spec("f{1..9223372036854775807}"), Some(("f#".to_string(), "1-9223372036854775807".to_string())));
+        assert_eq!(spec("f{1..18446744073709551615}"), None);
+        assert_eq!(spec("f{1..9223372036854775807..2}"), None);
+        assert_eq!(spec("frame.exr"), None);
+    }
+
+    #[test]
+    fn listed_sequences() {
+        let seq = ListedSequence::parse("render/frame.####.exr (1..250)").unwrap();
+        assert_eq!(("render/frame.", ".exr", 4, 250), (seq.prefix.as_str(), seq.suffix.as_str(), seq.pad, seq.frames.len()));
+        assert_eq!("render/frame.0007.exr", seq.path(7));
+        let seq = ListedSequence::parse("shot_v2/f%03d.exr (8..10, 3 frames @ 24fps = 0.1s) [3 duplicates]").unwrap();
+        assert_eq!(vec!["shot_v2/f008.exr", "shot_v2/f009.exr", "shot_v2/f010.exr"], seq.frames.iter().map(|n| seq.path(n)).collect::<Vec<_>>());
+        assert_eq!("take12/", ListedSequence::parse("take$F2/ (3..5)").unwrap().path(12));
+        assert_eq!("f05.exr", ListedSequence::parse("f##.exr      (1..9)").unwrap().path(5));
+        assert_eq!("a (b) 5.tif", ListedSequence::parse("a (b) #.tif (1..9) ✓").unwrap().path(5));
+        // The range is kept whole, however far apart its ends are.
+        let seq = ListedSequence::parse("f#.exr (-1..2000000000)").unwrap();
+        assert_eq!((2_000_000_002, "-1-2000000000".to_string()), (seq.frames.len(), seq.frames.to_string()));
+        assert_eq!("f-1.exr", seq.path(-1));
+        assert_eq!(None, ListedSequence::parse("notes (1..2).txt"));
+        assert_eq!(None, ListedSequence::parse("notes.txt"));
+    }
+
+    #[test]
+    fn wrapping_counters() {
+        assert_eq!(wrapped_span([9998, 9999, 0, 1], 10000), Some((9998, 1)));
+        assert_eq!(wrapped_span([5, 6, 8], 10000), Some((5, 8)));
+        assert_eq!(wrapped_span([0, 9999], 10000), Some((9999, 0)));
+        assert_eq!(wrapped_span([3], 10), Some((3, 3)));
+        assert_eq!(wrapped_span([], 10), None);
+    }
+
+    #[test]
+    fn number_gaps() {
+        assert_eq!(gaps([1, 2, 3]), vec![]);
+        assert_eq!(gaps([5, 1, 3, 9, 3]), vec![(2, 2), (4, 4), (6, 8)]);
+        assert_eq!(gaps([-2, 2]), vec![(-1, 1)]);
+        assert_eq!(gaps([]), vec![]);
+    }
+
+    #[test]
+    fn frame_time() {
+        assert_eq!("10.4s", format_frame_time(250, 24.0));
+        assert_eq!("0.0s", format_frame_time(0, 24.0));
+        assert_eq!("1m 0.0s", format_frame_time(1440, 24.0));
+        assert_eq!("1h 2m 5.0s", format_frame_time(3725 * 25, 25.0));
+        assert_eq!("4.2s", format_frame_time(100, 23.976));
+    }
+
+    #[test]
+    fn rename_plan() {
+        let p = |s: &str| PathBuf::from(s);
+        let steps = |plan: &RenamePlan| -> Vec<(String, String)> {
+            plan.steps().iter().map(|r| (r.from.display().to_string(), r.to.display().to_string())).collect()
+        };
+        let pair = |a: &str, b: &str| (a.to_string(), b.to_string());
+
+        // Shifting up renames the last file first.
+        let plan = RenamePlan::new((1..=3).map(|i| (p(&format!("f{}", i)), p(&format!("f{}", i + 1))))).unwrap();
+        assert_eq!(vec![pair("f3", "f4"), pair("f2", "f3"), pair("f1", "f2")], steps(&plan));
+        assert_eq!("f3 -> f4\nf2 -> f3\nf1 -> f2\n", plan.to_string());
+
+        // Swaps go through a temporary name.
+        let plan = RenamePlan::new([(p("a"), p("b")), (p("b"), p("a")), (p("c"), p("c"))]).unwrap();
+        assert_eq!(
+            vec![pair("a", "a.lsn-tmp"), pair("b", "a"), pair("a.lsn-tmp", "b")],
+            steps(&plan)
+        );
+        assert_eq!(2, plan.len());
+
+        // A chain leading into a cycle.
+        let plan = RenamePlan::new([(p("x"), p("a")), (p("a"), p("b")), (p("b"), p("a.lsn-tmp")), (p("a.lsn-tmp"), p("y"))]).unwrap();
+        assert_eq!(
+            vec![pair("a.lsn-tmp", "y"), pair("b", "a.lsn-tmp"), pair("a", "b"), pair("x", "a")],
+            steps(&plan)
+        );
+
+        let plan = RenamePlan::new([(p("x"), p("a")), (p("a"), p("b")), (p("b"), p("x"))]).unwrap();
+        assert_eq!(
+            vec![pair("x", "x.lsn-tmp"), pair("b", "x"), pair("a", "b"), pair("x.lsn-tmp", "a")],
+            steps(&plan)
+        );
+
+        assert!(RenamePlan::new([(p("a"), p("c")), (p("b"), p("c"))]).is_err());
+        assert!(RenamePlan::new([(p("a"), p("c")), (p("a"), p("d"))]).is_err());
+        assert!(RenamePlan::new([(p("a"), p("a"))]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn paths() {
+        let p = |s: &str| PathBuf::from(s);
+        assert_eq!(p("/a/c"), normalize_path(Path::new("/a/./b/../c")));
+        assert_eq!(p("/"), normalize_path(Path::new("/..")));
+        assert_eq!(p("../a"), normalize_path(Path::new("./../a")));
+        assert_eq!(p("."), normalize_path(Path::new("a/..")));
+
+        assert_eq!(p("c/d"), relative_path(Path::new("/a/b/c/d"), Path::new("/a/b")));
+        assert_eq!(p("../../x"), relative_path(Path::new("/a/x"), Path::new("/a/b/c")));
+        assert_eq!(p("."), relative_path(Path::new("/a/b"), Path::new("/a/b")));
+        assert_eq!(p("../b"), relative_path(Path::new("/a/b"), Path::new("/a/c")));
+        assert_eq!(p("shots/render"), relative_path(Path::new("shots/render"), Path::new(".")));
+    }
+
+    #[test]
+    fn file_kinds() {
+        let jpeg = FileKind::detect(b"\xff\xd8\xff\xe0\0\x10JFIF").unwrap();
+        assert_eq!(("image/jpeg", "jpg"), (jpeg.mime, jpeg.extension));
+        assert!(jpeg.has_extension(".JPG"));
+        assert!(jpeg.has_extension("jpeg"));
+        assert!(!jpeg.has_extension("exr"));
+        let exr = FileKind::detect(b"\x76\x2f\x31\x01\x02\0\0\0").unwrap();
+        assert_eq!(FileKind { mime: "image/x-exr", extension: "exr" }, exr);
+        assert_eq!("image/x-dpx", FileKind::detect(b"XPDS\0\0\x20\0").unwrap().mime);
+        assert_eq!("image/vnd.radiance", FileKind::detect(b"#?RADIANCE\n").unwrap().mime);
+        assert!(FileKind::detect(b"\x89HDF\r\n\x1a\n").unwrap().has_extension("hdf5"));
+        assert_eq!("wav", FileKind::detect(b"RIFF\x24\0\0\0WAVEfmt ").unwrap().extension);
+        assert_eq!("video/quicktime", FileKind::detect(b"\0\0\0\x14ftypqt  \0\0\0\0").unwrap().mime);
+        assert_eq!(None, FileKind::detect(b"RIF"));
+        assert_eq!(None, FileKind::detect(b"plain text"));
+        assert!(FileKind::is_known_extension("tif"));
+        assert!(FileKind::is_known_extension(".TIFF"));
+        assert!(FileKind::is_known_extension("dpx"));
+        assert!(!FileKind::is_known_extension("txt"));
+    }
+
+    #[test]
+    fn sizes_for_humans() {
+        assert_eq!("0", format_size(0));
+        assert_eq!("1023", format_size(1023));
+        assert_eq!("1.0K", format_size(1024));
+        assert_eq!("1.5K", format_size(1536));
+        assert_eq!("40M", format_size(40 * 1024 * 1024));
+        assert_eq!("2.0G", format_size(2 << 30));
+
+        assert_eq!("0", format_thousands(0));
+        assert_eq!("999", format_thousands(999));
+        assert_eq!("3,120", format_thousands(3120));
+        assert_eq!("1,234,567", format_thousands(1234567));
+
+        assert_eq!("1,234,567", SizeUnit::Bytes.format(1234567));
+        assert_eq!("1,206K", SizeUnit::Kb.format(1234567));
+        assert_eq!("0K", SizeUnit::Kb.format(0));
+        assert_eq!("1M", SizeUnit::Mb.format(1));
+        assert_eq!("1.2M", SizeUnit::Auto.format(1234567));
+        assert_eq!(Ok(SizeUnit::Gb), "GB".parse());
+
+        assert_eq!(None, SizeStats::new([]));
+        let stats = SizeStats::new([2, 4, 4, 4, 5, 5, 7, 9]).unwrap();
+        assert_eq!((2, 9, 5.0, 2.0), (stats.min, stats.max, stats.mean, stats.std_dev));
+        assert_eq!("min 2, max 9, mean 5, sd 2", stats.to_string());
+    }
+
+    #[test]
+    fn truncation() {
+        assert_eq!(5, display_width("\x1b[1;34mhello\x1b[0m"));
+        assert_eq!(4, display_width(&hyperlink("file:///x", "link")));
+        assert_eq!(2, display_width("✓a"));
+
+        assert_eq!("short#.exr", truncate_middle("short#.exr", 5, 20));
+        assert_eq!("a_ve…stem#.exr", truncate_middle("a_very_long_stem#.exr", 5, 14));
+        assert_eq!("a…#.exr", truncate_middle("a_very_long_stem#.exr", 5, 3));
+        assert_eq!("#.exr", truncate_middle("#.exr", 5, 3));
+    }
+
+    #[test]
+    fn radix() {
+        let hex = Matcher::default().with_radix(Radix::Hex);
+        let split = hex.split(b"cap_0x1A.raw").unwrap();
+        assert_eq!((&b"cap_0x"[..], Some(26)), (split.stem, split.number()));
+        assert_eq!(None, hex.split(b"file.exe"));
+        assert_eq!(Some(0xbeef1), hex.split(b"beef1").and_then(|s| s.number()));
+        // Trailing runs of hex letters alone fall back to the number before them.
+        let split = hex.split(b"shot.0a1f.cafe").unwrap();
+        assert_eq!((&b"shot."[..], Some(0xa1f), &b".cafe"[..]), (split.stem, split.number(), split.ext));
+        let split = hex.split(b"mesh_1f.dae").unwrap();
+        assert_eq!((&b"mesh_"[..], Some(0x1f), &b".dae"[..]), (split.stem, split.number(), split.ext));
+
+        let auto = Matcher::default().with_radix(Radix::Auto);
+        let split = auto.split(b"cap_0x1f.raw").unwrap();
+        assert_eq!((Radix::Hex, Some(31)), (split.radix, split.number()));
+        let split = auto.split(b"frame.0010.exr").unwrap();
+        assert_eq!((Radix::Dec, Some(10)), (split.radix, split.number()));
+        let split = auto.split(b"file1.abc").unwrap();
+        assert_eq!((&b"file"[..], Some(1)), (split.stem, split.number()));
+
+        assert_eq!(None, Radix::Dec.parse(b"+1"));
+        assert_eq!(Ok(Radix::Auto), "auto".parse());
+    }
+
+    #[test]
+    fn signed_numbers() {
+        let signed = Matcher::default().with_signed(true);
+        let split = signed.split(b"frame-0010.exr").unwrap();
+        assert_eq!((&b"frame"[..], &b"-0010"[..], Some(-10)), (split.stem, split.num, split.number()));
+        let split = signed.split(b"frame0010.exr").unwrap();
+        assert_eq!((&b"frame"[..], Some(10)), (split.stem, split.number()));
+        let split = signed.split(b"-5").unwrap();
+        assert_eq!((&b""[..], Some(-5)), (split.stem, split.number()));
+
+        let split = Matcher::default().split(b"frame-0010.exr").unwrap();
+        assert_eq!((&b"frame-"[..], Some(10)), (split.stem, split.number()));
+
+        let regex = Matcher::from_pattern(DEFAULT_PATTERN).unwrap().with_signed(true);
+        assert_eq!(Some(-3), regex.split(b"a-3.txt").and_then(|s| s.number()));
+    }
+
+    #[test]
+    fn dates_in_names() {
+        let date = |y, m, d| chrono::NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let split = split_date(b"backup_20240105.sql").unwrap();
+        assert_eq!((&b"backup_"[..], &b".sql"[..], date(2024, 1, 5)), (split.stem, split.ext, split.date));
+        let split = split_date(b"log-2023-12-31").unwrap();
+        assert_eq!((&b"log-"[..], &b""[..], date(2023, 12, 31)), (split.stem, split.ext, split.date));
+        let split = split_date(b"20240101_to_20240201.txt").unwrap();
+        assert_eq!((&b"20240101_to_"[..], date(2024, 2, 1)), (split.stem, split.date));
+        assert_eq!(None, split_date(b"frame.0001.exr"));
+        assert_eq!(None, split_date(b"id_202401051.txt"));
+        assert_eq!(None, split_date(b"bad_20241301.txt"));
+    }
+}
@@ -1,283 +1,3921 @@
-use std::{path::PathBuf, fs::Metadata, time::SystemTime, ffi::OsString};
+use std::{path::{Path, PathBuf}, time::{Duration, Instant, SystemTime}, ffi::{OsStr, OsString}};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, HashMap};
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
 
 use chrono::{DateTime, Local};
-use clap::{Parser, FromArgMatches, Args};
-use colored::Colorize;
+use clap::{Parser, FromArgMatches, Args, builder::TypedValueParser};
 use walkdir::WalkDir;
 
 use indexmap::IndexMap;
+use colors::{Colors, Kind};
+use config::Config;
+use pager::{Output, Paging};
+use lsn::{FileGroup, FileKind, FrameSet, Matcher, Member, Meta, PatternStyle, QuotingStyle, Radix, Scanner, SizeUnit};
+
+mod archive;
+mod cache;
+mod colors;
+mod config;
+mod owner;
+mod pager;
+#[cfg(feature = "remote")]
+mod remote;
 
 const ABOUT: &str = "
 lsn lists directory contents with large numbered file lists by grouping files with common roots together.";
 
+const LONG_ABOUT: &str = "
+lsn lists directory contents with large numbered file lists by grouping files with common roots together.
+
+Files whose names end in a number, optionally followed by an extension, are grouped by the text before \
+the number (the stem) and the extension. Each group is shown once with `#` in place of the number \
+followed by the range of numbers found, so `frame1.exr` through `frame250.exr` are listed as \
+`frame#.exr (1..250)`. Subcommands accept the same `#` syntax to refer to a sequence.
+
+Default options may be set in the LSN_OPTS environment variable, which is parsed like command line \
+arguments given before any others. Presets defined in the config file, such as `wrangle = \"-l \
+--sort mtime:desc\"` in its `[presets]` table, are used as `lsn @wrangle PATH` and stand in for their \
+flags, which may name other presets. They are only recognized where a flag could go, not as the \
+value of an option. LSN_TIME_STYLE sets the default `--time-style`, and entry colors \
+are read from LS_COLORS and then LSN_COLORS in the same `di=01;34:*.exr=33` format.
+
+Entries matching the patterns of a `.lsnignore` file in a scanned directory are hidden, such as \
+`*.meta` or `cache/` to hide pipeline sidecar files. The files use gitignore syntax and apply to the \
+directory they are in and everything below it, unless `--no-ignore` is given.
+
+The exit status is 0 on success, 1 if some entries couldn't be read or `lsn check` found problems and 2 for usage errors or when \
+nothing could be listed.
+
+Further settings are read from the TOML file given by LSN_CONFIG, which defaults to \
+`$XDG_CONFIG_HOME/lsn/config.toml` or `~/.config/lsn/config.toml`. Its `[open]` table maps extensions \
+to the commands used by `lsn open`, in which `{}` is replaced by the first frame and `{pattern}` by a \
+printf-style pattern such as `frame.%04d.exr`. Its `[colors]` table maps extensions such as `3dv` or \
+globs such as `*.bgeo.sc` to styles like `bold underline yellow` or SGR codes like `01;33`, which are \
+added to the colors from LS_COLORS. Times are colored by age, bold green within the last hour, green \
+within a day and yellow within a week, which its `[ages]` table replaces with styles for ages such as \
+`1h = \"bold green\"` or `2d = \"green\"`. Groups with stems in its `hide_stems` list, such as \
+`[\"autosave\"]`, are hidden as with `--hide-stem`.";
+
 const EXAMPLES: &str = "
 EXAMPLES:
 
 List numbered files:
 
 $ lsn
+
+List largest groups first, breaking ties by name:
+
+$ lsn --sort size:desc,name
+
+Find the ten largest sequences and files in a project:
+
+$ lsn --depth 5 --top 10 -l projects/show
+
+Link a sequence with gaps into a densely numbered directory starting at 1001:
+
+$ lsn link 'render/frame#.exr' dense --renumber --start 1001
+
+Open a sequence with the viewer configured for its extension:
+
+$ lsn open 'render/frame#.exr'
+
+Convert each frame of a sequence, or encode the whole sequence at once:
+
+$ lsn exec 'render/frame#.exr' -- oiiotool {} -o {}.png
+$ lsn exec 'render/frame#.png' -- ffmpeg -start_number {start} -i {pattern} out.mp4
+
+Save a listing and later print the paths of all frames it covers without scanning again:
+
+$ lsn --depth 2 --pattern-style hash render > frames.txt
+$ lsn expand < frames.txt
+
+Verify that a delivered archive contains complete sequences without extracting it:
+
+$ lsn --archive --depth 3 delivery.tar
+
+Work out which odd frames up to 100 to resubmit, skipping frames 40 to 50:
+
+$ lsn frames 1-100 --subtract 40-50 --intersect 1-200x2
+
+Archive a finished sequence with zstd compression:
+
+$ lsn tar 'render/frame#.exr' -o frames.tar.zst
+
+Preview renaming a sequence to four digit numbers:
+
+$ lsn repad 'render/frame#.exr' --width 4 --dry-run
+
+Check all sequences in a delivery for problems, writing a JSON report:
+
+$ lsn --depth 3 check delivery --json > report.json
+
+Only publish a render once all of its frames exist:
+
+$ lsn exists 'render/frame#.exr' --frames 1-250 --quiet && publish render
+
+Compare the passes rendered in each version directory of a shot:
+
+$ lsn --depth 2 --across-dirs-report --size-unit auto shots/sh010
 ";
 
 #[derive(Parser, Debug)]
-#[clap(author, version, about = ABOUT, name = "lsn")]
-#[clap(after_long_help(EXAMPLES))]
+#[clap(author, version, about = ABOUT, long_about = LONG_ABOUT, name = "lsn")]
+#[clap(after_long_help(EXAMPLES), args_override_self = true)]
 struct Opt {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
     /// A directory whose contents need to be printed.
+    ///
+    /// A sequence such as `frame#.exr` lists only that sequence, and may be followed by `@FRAMES`
+    /// (e.g. `frame#.exr@1-100`) to check which of the given frames exist.
+    ///
+    /// A brace expression of numbers in the file name, such as `frame{1..100}.exr`,
+    /// `frame{1..100..2}.exr` or `frame{1,5,10..20}.exr`, likewise checks which of those frames
+    /// exist.
+    ///
+    /// Glob patterns such as `shots/*/render` are expanded unless the path exists as given or
+    /// `--literal` is used.
+    ///
+    /// An `s3://bucket/prefix` URL lists the objects under the prefix when lsn is built with the
+    /// `remote` feature, grouping keys like file names. Credentials and region are read from the
+    /// `AWS_*` environment variables.
     #[clap(default_value = ".")]
     path: String,
 
+    /// Restrict a listed sequence to the given frames (e.g. `1-100`, `1-100x2` or `1,5,10-20`),
+    /// reporting any that are missing.
+    #[clap(long, value_name = "FRAMES", allow_hyphen_values = true)]
+    frames: Option<FrameSet>,
+
+    /// Show hidden entries: those starting with `.`, and on Windows and macOS those with the
+    /// hidden attribute.
     #[clap(short, long)]
     all: bool,
 
+    /// List entries matching the patterns in `.lsnignore` files of the scanned directories, which
+    /// are otherwise hidden. The files use gitignore syntax.
+    #[clap(long)]
+    no_ignore: bool,
+
+    /// Fail with a list of the matches when a sequence given to a subcommand, such as `frame#`,
+    /// matches several, rather than asking which is meant.
+    #[clap(long)]
+    no_interactive: bool,
+
+    /// Don't sort by name, listing entries in the order they were found.
     #[clap(short = 'U', long = "unsorted")]
     unsorted: bool,
 
+    /// List directories themselves instead of their contents.
+    #[clap(short = 'd', long)]
+    directory: bool,
+
+    /// Show the directory of each entry as an absolute path.
+    #[clap(long, conflicts_with = "relative_to")]
+    absolute: bool,
+
+    /// Show the directory of each entry relative to the given directory.
+    #[clap(long, value_name = "PATH")]
+    relative_to: Option<PathBuf>,
+
+    /// Don't descend into directories on other filesystems.
+    #[clap(short = 'x', long)]
+    one_file_system: bool,
+
+    /// Show the mount point of the filesystem each directory is on.
+    #[clap(long)]
+    mounts: bool,
+
+    /// Treat the path literally instead of as a glob pattern.
+    #[clap(long)]
+    literal: bool,
+
+    /// List the entries inside the tar or zip archive given as the path without extracting it.
+    /// Tar archives may be zstd-compressed.
+    #[clap(long)]
+    archive: bool,
+
+    /// Maximum depth of directories to descend into.
     #[clap(long, default_value = "1")]
     depth: usize,
 
+    /// Follow symbolic links while walking directories. Links back to a directory above them and
+    /// links to a directory that was already listed are skipped with a warning.
     #[clap(short = 'L', long)]
     follow_links: bool,
 
+    /// Stop scanning after this many entries and list those found so far, as a safety valve for
+    /// huge trees.
+    #[clap(long, value_name = "N")]
+    max_entries: Option<usize>,
+
+    /// Show the size, times and type of the targets of symbolic links instead of the links
+    /// themselves.
+    #[clap(long)]
+    dereference: bool,
+
+    /// With `--dereference`, count the size of a file once for each link to it in a group rather
+    /// than only once.
+    #[clap(long, requires = "dereference")]
+    count_links: bool,
+
+    /// Print one summary row per directory with its number of groups and files, total size and
+    /// newest modification time.
+    #[clap(long)]
+    by_dir: bool,
+
+    /// Print one line per directory counting its sequences and files by extension, such as
+    /// `exr: 4 seqs/1,200 files, nk: 7 files`.
+    #[clap(long)]
+    ext_summary: bool,
+
+    /// Print the sequences whose names appear in more than one directory, such as `beauty#.exr`
+    /// in both `v012/` and `v013/`, with the range, count and size of each variant and how the
+    /// count and size differ from the variant above it.
+    #[clap(long)]
+    across_dirs_report: bool,
+
+    /// Print a line naming the columns of `-l`, `-i` and `--by-dir` output, such as SIZE,
+    /// MODIFIED and NAME.
+    #[clap(long)]
+    header: bool,
+
+    /// Show at most N single files per directory, summarizing the rest as `… and M other files`
+    /// to keep the focus on sequences.
+    #[clap(long, value_name = "N")]
+    max_singles: Option<usize>,
+
+    /// Only list the N largest groups by total size, or by number of files with `--top-by count`,
+    /// largest first unless sorted otherwise.
+    #[clap(long, value_name = "N")]
+    top: Option<usize>,
+
+    /// What `--top` ranks groups by.
+    #[clap(long, value_enum, value_name = "KEY", default_value_t = TopBy::Size, requires = "top")]
+    top_by: TopBy,
+
+    /// How to write the number of sequences: `lsn` (`frame.#.exr`), `hash` (`frame.####.exr`),
+    /// `printf` (`frame.%04d.exr`) or `houdini` (`frame.$F4.exr`).
+    #[clap(long, value_name = "STYLE", default_value = "lsn", value_parser = clap::builder::PossibleValuesParser::new(["lsn", "hash", "printf", "houdini"]).map(|s| s.parse::<PatternStyle>().unwrap()))]
+    pattern_style: PatternStyle,
+
+    /// Collapse sequences in the same directory whose names share a prefix up to the first `_`,
+    /// `.` or `-`, such as the passes in `shotA_diffuse.#.exr` and `shotA_normal.#.exr`, under a
+    /// `shotA_*` node listing the rest of each name.
+    #[clap(long)]
+    prefix_tree: bool,
+
+    /// Don't shorten names that don't fit within the terminal width.
+    #[clap(long)]
+    no_truncate: bool,
+
+    /// Group numbered directories (e.g. `take001/`, `take002/`) like files.
+    #[clap(long)]
+    group_dirs: bool,
+
+    /// Group names containing a `YYYYMMDD` or `YYYY-MM-DD` date (e.g. `backup_20240105.sql`) by
+    /// the text around it, showing the range of dates and the number of missing days.
+    #[clap(long)]
+    dates: bool,
+
+    /// Show sequences that differ only in extension as one entry listing the extensions, e.g.
+    /// `frame# {.exr,.jpg} (1..250)`, noting extensions with missing frames.
+    #[clap(long)]
+    merge_ext: bool,
+
+    /// Treat sequences whose stems differ only in trailing characters from the given set as one,
+    /// e.g. `--separators '._-'` lists `frame.0001.exr` and `frame_0002.exr` as
+    /// `frame{.,_}#.exr (1..2)`.
+    #[clap(long, value_name = "CHARS")]
+    separators: Option<String>,
+
+    /// Print directories, sequences and single files under separate headings.
+    #[clap(long, conflicts_with_all = ["by_dir", "zero"])]
+    sections: bool,
+
+    /// List numerically equal files with different zero padding (e.g. `frame1.exr` and
+    /// `frame0001.exr`) as separate groups, shown with one `#` per padded digit.
+    #[clap(long)]
+    split_padding: bool,
+
+    /// Split names into stem, number and extension with a regex having `stem`, `num` and `ext`
+    /// named groups instead of the built-in scanner. Without a value, the regex equivalent of the
+    /// scanner is used.
+    #[clap(long, value_name = "REGEX", num_args = 0..=1, default_missing_value = lsn::DEFAULT_PATTERN, value_parser = Matcher::from_pattern)]
+    pattern: Option<Matcher>,
+
+    /// Base of the numbers in names. `hex` treats trailing hex digits as the number, and `auto`
+    /// reads numbers after `0x` (as in `cap_0x1A.raw`) as hex and others as decimal.
+    #[clap(long, default_value = "dec", value_parser = clap::builder::PossibleValuesParser::new(["dec", "hex", "auto"]).map(|s| s.parse::<Radix>().unwrap()))]
+    radix: Radix,
+
+    /// Read a `-` right before a number as its sign, so `frame-0010.exr` through `frame0010.exr`
+    /// form one sequence from -10 to 10.
+    #[clap(long)]
+    signed: bool,
+
+    /// Treat numbers as a counter that wraps around to 0 after N - 1, as camera files numbered
+    /// `9998, 9999, 0000, 0001` do, showing the contiguous span `9998..1` and counting gaps within
+    /// it.
+    #[clap(long, value_name = "N", value_parser = clap::value_parser!(u64).range(2..).map(|n| n as usize))]
+    wrap: Option<usize>,
+
+    /// Show the running time of sequences at the given frame rate.
+    #[clap(long, value_name = "N", value_parser = parse_fps)]
+    fps: Option<f64>,
+
+    /// Show the smallest, largest and mean member size and the standard deviation of sizes for
+    /// each sequence, to help spot bad frames.
+    #[clap(long)]
+    stats: bool,
+
+    /// Show the type of each group detected from the contents of its first file, flagging groups
+    /// whose extension belongs to another type, such as `.exr` files that are actually JPEGs.
+    #[clap(long)]
+    mime: bool,
+
+    /// Mark each sequence with `✓` if it is contiguous or `!N` if N frames are missing.
+    #[clap(long)]
+    indicators: bool,
+
+    /// Show inode numbers and the number of hard-linked members in each sequence.
+    #[clap(short = 'i', long)]
+    inode: bool,
+
+    /// Show sizes and modification times.
     #[clap(short = 'l', long)]
     long: bool,
 
+    /// Times to show with `-l` as separate columns, in the order given. Groups show the latest
+    /// modification and access times and the earliest creation time of their members.
+    #[clap(long, value_enum, value_delimiter = ',', value_name = "TIMES", default_value = "modified")]
+    show_times: Vec<TimeField>,
+
+    /// Unit of sizes: `bytes` (with thousands separators), `kb`, `mb`, `gb`, or `auto` for the
+    /// largest fitting unit, e.g. `1.5K` or `40M`. Sizes are plain bytes by default.
+    #[clap(long, value_name = "UNIT", value_parser = clap::builder::PossibleValuesParser::new(["bytes", "kb", "mb", "gb", "auto"]).map(|s| s.parse::<SizeUnit>().unwrap()))]
+    size_unit: Option<SizeUnit>,
+
+    /// Color sizes from green to red by how large they are relative to the largest in the
+    /// listing, so that outliers stand out.
+    #[clap(long)]
+    size_heat: bool,
+
+    /// Sort by a comma separated list of keys, each optionally suffixed with `:desc`.
+    ///
+    /// Keys are compared in the order given, falling back to name ordering unless `-U` is set.
+    /// Valid keys are `name`, `size`, `mtime`, `created`, `ext`, `count`, `start`, `dir` and
+    /// `gaps`, the number of gaps in a sequence followed by its number of missing frames. `gaps`
+    /// sorts in descending order unless suffixed with `:asc`, so the most broken sequences come
+    /// first.
+    /// When listing more than one level deep, ties are broken by directory last.
+    #[clap(long, value_delimiter = ',', value_name = "KEYS")]
+    sort: Vec<SortKey>,
+
+    /// Shorthand for `--sort mtime`, applied after any keys given to `--sort`.
     #[clap(short = 't', long)]
     sort_by_modified: bool,
 
+    /// Shorthand for `--sort created`, the earliest creation time of a group's files, applied after
+    /// any keys given to `--sort` and `-t`.
+    #[clap(short = 'c', long)]
+    sort_by_created: bool,
+
+    /// Shorthand for `--sort size`, applied after any keys given to `--sort`, `-t` and `-c`.
     #[clap(short = 'S', long)]
     sort_by_size: bool,
 
+    /// Reverse the sort order.
     #[clap(short = 'r', long)]
     reverse: bool,
 
-    #[clap(short = 'n', long)]
+    /// List sequences before single files, sorting each as usual. `--reverse` doesn't change
+    /// which come first.
+    #[clap(long)]
+    sequences_first: bool,
+
+    /// Only list entries of the given type. May be repeated to list several types.
+    #[clap(long = "type", value_enum, value_delimiter = ',', value_name = "TYPE")]
+    types: Vec<EntryType>,
+
+    /// Only list groups whose stem matches GLOB, regardless of their numbers. The stem is the
+    /// name before the number without trailing `.`, `_` or `-`, e.g. `beauty_diffuse` for
+    /// `beauty_diffuse.#.exr`, or the name without its extension for unnumbered entries.
+    #[clap(long, value_name = "GLOB", value_parser = glob::Pattern::new)]
+    name: Option<glob::Pattern>,
+
+    /// Only list groups whose stem, as for `--name`, contains a match of the regex PATTERN.
+    #[clap(long, value_name = "PATTERN", value_parser = regex::bytes::Regex::new)]
+    regex: Option<regex::bytes::Regex>,
+
+    /// Hide groups whose stem, as for `--name`, matches NAME, which may be a glob. May be
+    /// repeated, and is added to the `hide_stems` list of the config file. Hidden groups are
+    /// shown with `--all`.
+    #[clap(long, value_name = "NAME", value_parser = glob::Pattern::new)]
+    hide_stem: Vec<glob::Pattern>,
+
+    /// Only list entries owned by USER, given by name or numeric ID. Sequences are listed if all
+    /// of their files are owned by USER.
+    #[clap(long, value_name = "USER", value_parser = owner::parse_user)]
+    owner: Option<u32>,
+
+    /// Only list entries whose group is GROUP, given by name or numeric ID. Sequences are listed
+    /// if all of their files belong to GROUP.
+    #[clap(long, value_name = "GROUP", value_parser = owner::parse_group)]
+    group: Option<u32>,
+
+    /// Show user and group IDs rather than names in the long listing.
+    #[clap(long)]
+    numeric_ids: bool,
+
+    /// Only list entries whose total size exceeds SIZE (e.g. `500K`, `10G`).
+    #[clap(long, value_name = "SIZE", value_parser = lsn::parse_size)]
+    larger_than: Option<u64>,
+
+    /// Only list entries whose total size is below SIZE (e.g. `500K`, `10G`).
+    #[clap(long, value_name = "SIZE", value_parser = lsn::parse_size)]
+    smaller_than: Option<u64>,
+
+    /// Only list entries modified after the given time, either a duration ago (e.g. `1h`, `2d`) or
+    /// a date (e.g. `2024-01-05`).
+    #[clap(long, value_name = "DURATION|DATE", value_parser = parse_time)]
+    newer_than: Option<SystemTime>,
+
+    /// Only list entries last modified before the given time, either a duration ago (e.g. `1h`,
+    /// `2d`) or a date (e.g. `2024-01-05`).
+    #[clap(long, value_name = "DURATION|DATE", value_parser = parse_time)]
+    older_than: Option<SystemTime>,
+
+    /// Enclose names in double quotes, escaping special characters.
+    #[clap(short = 'Q', long, overrides_with = "escape")]
+    quote_name: bool,
+
+    /// Escape spaces and nonprinting characters in names with backslashes.
+    #[clap(short = 'b', long, overrides_with = "quote_name")]
+    escape: bool,
+
+    /// Print one NUL terminated path per entry, for piping into `xargs -0`.
+    #[clap(short = '0', long)]
+    zero: bool,
+
+    /// With `--zero`, print every member of a sequence instead of its `#` pattern.
+    #[clap(long, requires = "zero")]
+    expand: bool,
+
+    /// How to format times: `default`, `iso`, `long-iso`, `full-iso` or `+FORMAT` with a strftime
+    /// style FORMAT.
+    #[clap(long, env = "LSN_TIME_STYLE", default_value = "default", value_parser = parse_time_style)]
+    time_style: String,
+
+    /// Change in the number of files of each group since the previous `--refresh`, by path, or
+    /// `None` for new groups.
+    #[clap(skip)]
+    changes: HashMap<OsString, Option<isize>>,
+
+    /// Colors of entries, read from `LS_COLORS` and then `LSN_COLORS`.
+    #[clap(skip = Colors::default())]
+    colors: Colors,
+
+    /// Counts and durations reported by `--timing`.
+    #[clap(skip = Timings::new())]
+    timings: Timings,
+
+    /// Append `*` to executables in addition to the default `/`, `@`, `|` and `=` indicators.
+    #[clap(short = 'F', long, overrides_with_all = ["slash", "indicator_style"])]
+    classify: bool,
+
+    /// Only append `/` to directories.
+    #[clap(short = 'p', overrides_with_all = ["classify", "indicator_style"])]
+    slash: bool,
+
+    /// Indicators appended to names to show their file type. Defaults to `file-type`, or `none`
+    /// with `--classic`.
+    #[clap(long, value_enum, value_name = "STYLE", overrides_with_all = ["classify", "slash"])]
+    indicator_style: Option<IndicatorStyle>,
+
+    /// Behave like a minimal `ls`: list every entry on its own without grouping numbered names
+    /// or appending type indicators. Other options still apply.
+    #[clap(long, overrides_with = "no_classic")]
+    classic: bool,
+
+    /// Group numbered names, overriding an earlier `--classic` such as one from `LSN_OPTS`.
+    #[clap(long, overrides_with = "classic")]
+    no_classic: bool,
+
+    /// Output format of the listing.
+    ///
+    /// The objects of `json` and `ndjson` output follow the schema in `schema/lsn.schema.json`,
+    /// whose version is only bumped when fields are removed or change meaning.
+    #[clap(long, value_enum, default_value_t = Format::Text, conflicts_with_all = ["zero", "by_dir", "sections"])]
+    format: Format,
+
+    /// Shorthand for `--format json`.
+    #[clap(long, conflicts_with_all = ["format", "zero", "by_dir", "sections"])]
+    json: bool,
+
+    /// Send output through `$PAGER` (or `less -R`).
+    #[clap(long, value_enum, value_name = "WHEN", default_value_t = Paging::Never)]
+    paging: Paging,
+
+    /// Don't print warnings.
+    #[clap(short = 'q', long)]
+    quiet: bool,
+
+    /// When to color text output: `auto` colors it for terminals, `always` also when piped, such
+    /// as into `less -R`, and `never` not at all. Other formats are never colored.
+    #[clap(long, value_enum, value_name = "WHEN", default_value_t = ColorWhen::Auto, num_args = 0..=1, default_missing_value = "always", overrides_with = "nocolor")]
+    color: ColorWhen,
+
+    /// Disable colored output, like `--color never`.
+    #[clap(short = 'n', long, overrides_with = "color")]
     nocolor: bool,
+
+    /// Recognize trailing version tokens (e.g. `comp_v001.nk`) and show the latest version of each
+    /// versioned group.
+    #[clap(long)]
+    versions: bool,
+
+    /// Only list the highest numbered member of each group, by its file name.
+    #[clap(long)]
+    latest: bool,
+
+    /// Treat groups numbered within 1001-1999 as UDIM texture sets, reporting tile counts and
+    /// missing tiles.
+    #[clap(long)]
+    udim: bool,
+
+    /// Print the groups of each directory as soon as it has been scanned instead of sorting the
+    /// whole listing at once.
+    #[clap(long)]
+    stream: bool,
+
+    /// List again every interval, such as `2` or `1m` for seconds or minutes, marking groups that
+    /// are new or whose number of files or range changed since the previous listing.
+    #[clap(long, value_name = "SECS", value_parser = lsn::parse_duration, conflicts_with = "stream")]
+    refresh: Option<Duration>,
+
+    /// With `--refresh`, append the frames that appeared, changed or were removed since the
+    /// previous listing, and sequences whose gaps were filled, to FILE as lines of JSON.
+    #[clap(long, value_name = "FILE", requires = "refresh")]
+    log: Option<PathBuf>,
+
+    /// Show the recursive size of each directory's contents instead of the size of the directory
+    /// entry itself.
+    #[clap(long)]
+    du: bool,
+
+    /// Reuse the entries of directories listed before from `~/.cache/lsn` if they haven't been
+    /// modified since, avoiding reading large directories again. Applies to listings one level
+    /// deep without `--du`.
+    #[clap(long)]
+    cache: bool,
+
+    /// Don't show a progress line on stderr when scanning takes a while.
+    #[clap(long)]
+    no_progress: bool,
+
+    /// Print the number of entries scanned and groups formed to stderr, along with the time spent
+    /// walking, reading metadata, sorting and printing.
+    #[clap(long, conflicts_with = "refresh")]
+    timing: bool,
+
+    /// Wrap printed names in OSC 8 hyperlinks pointing at their containing directory.
+    #[clap(long)]
+    hyperlink: bool,
 }
 
-#[derive(Clone, Debug)]
-pub struct Meta {
-    modified: Option<SystemTime>,
-    accessed: Option<SystemTime>,
-    created: Option<SystemTime>,
-    size: u64,
-    is_dir: bool,
-    is_symlink: bool,
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Create a directory of symlinks pointing at the members of a sequence.
+    Link(LinkOpt),
+    /// Pack the members of a sequence into a tar archive.
+    Tar(TarOpt),
+    /// Rename the members of a sequence to a uniform zero padding width.
+    Repad(RepadOpt),
+    /// Create empty files for the given frames of a sequence, skipping those that exist.
+    Touch(TouchOpt),
+    /// Print the path of every frame of sequences given as lines of lsn output, such as
+    /// `frame.####.exr (1..250)`, without scanning their directories.
+    Expand(ExpandOpt),
+    /// Print the result of combining, intersecting and subtracting frame sets such as `1-100` or
+    /// `1-200x2`.
+    Frames(FramesOpt),
+    /// Run a command for each member of a sequence, or once per sequence with `{pattern}`,
+    /// `{start}` or `{end}`.
+    Exec(ExecOpt),
+    /// Open a sequence or file with the application configured for its extension.
+    Open(OpenOpt),
+    /// Print a summary of a single sequence: its range and gaps, padding, sizes and times.
+    Stat(StatOpt),
+    /// Check sequences for gaps, mixed padding, empty frames and duplicate numbers, exiting with
+    /// status 1 if any are found.
+    Check(CheckOpt),
+    /// Exit with status 0 if the given frames of a sequence all exist, and otherwise with status 1
+    /// after printing those that are missing.
+    Exists(ExistsOpt),
+    /// Print a roff man page to stdout.
+    Man,
 }
 
-impl From<Metadata> for Meta {
-    fn from(value: Metadata) -> Self {
-        Meta {
-            modified: value.modified().ok(),
-            accessed: value.accessed().ok(),
-            created: value.created().ok(),
-            size: value.len(),
-            is_dir: value.is_dir(),
-            is_symlink: value.is_symlink(),
-        }
-    }
+#[derive(Args, Debug)]
+struct LinkOpt {
+    /// The sequence to link, with `#` standing in for the number (e.g. `frame#.exr`).
+    spec: String,
+
+    /// Directory in which to create the links. It is created if it doesn't exist.
+    dest: PathBuf,
+
+    /// Number the links contiguously, closing any gaps in the sequence. Fails if members share a
+    /// number, such as `f01` and `f1`.
+    #[clap(long)]
+    renumber: bool,
+
+    /// First number used when renumbering. Defaults to the first number in the sequence.
+    #[clap(long, requires = "renumber")]
+    #[clap(allow_negative_numbers = true)]
+    start: Option<isize>,
+
+    /// What to do with files already at the paths of the links. Links that already point at the
+    /// right member are always kept.
+    #[clap(long, value_enum, value_name = "ACTION", default_value_t = Existing::Fail)]
+    existing: Existing,
+
+    /// Print the links that would be created without creating them.
+    #[clap(long, short = 'n')]
+    dry_run: bool,
+}
+
+/// How `lsn link` treats files already at the paths of the links it creates.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum Existing {
+    /// Fail before creating any link.
+    Fail,
+    /// Leave them in place and create only the other links.
+    Skip,
+    /// Replace them with the links. Directories are never replaced.
+    Overwrite,
+}
+
+#[derive(Args, Debug)]
+struct TarOpt {
+    /// The sequence to archive, with `#` standing in for the number (e.g. `frame#.exr`).
+    spec: String,
+
+    /// Archive to create. Names ending in `.zst` or `.tzst` are zstd-compressed.
+    #[clap(long, short)]
+    output: PathBuf,
+
+    /// Compress with zstd regardless of the output name.
+    #[clap(long)]
+    zstd: bool,
+}
+
+#[derive(Args, Debug)]
+struct RepadOpt {
+    /// The sequence to rename, with `#` standing in for the number (e.g. `frame#.exr`).
+    spec: String,
+
+    /// Number of digits to pad each number to.
+    #[clap(long, short)]
+    width: usize,
+
+    /// Print the renames without performing them.
+    #[clap(long, short = 'n')]
+    dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+struct TouchOpt {
+    /// The sequence to create, with `#` standing in for the number (e.g. `frame#.exr`). Numbers
+    /// are zero padded to the number of `#`s if there are several, and otherwise to the padding
+    /// of the existing sequence, if any.
+    spec: String,
+
+    /// Frames to create (e.g. `1-100`, `1-100x2` or `1,5,10-20`).
+    #[clap(long, value_name = "FRAMES", allow_hyphen_values = true)]
+    frames: FrameSet,
+
+    /// Print the files that would be created without creating them.
+    #[clap(long, short = 'n')]
+    dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+struct ExistsOpt {
+    /// The sequence to look for, with `#` standing in for the number (e.g. `frame#.exr`).
+    spec: String,
+
+    /// Frames that must exist (e.g. `1-250`, `1-100x2` or `1,5,10-20`).
+    #[clap(long, value_name = "FRAMES", allow_hyphen_values = true)]
+    frames: FrameSet,
+
+    /// Don't print the missing frames, only exit with the status.
+    #[clap(short = 'q', long)]
+    quiet: bool,
+}
+
+#[derive(Args, Debug)]
+struct ExpandOpt {
+    /// Lines of lsn output, read from standard input if none are given. Lines that don't describe
+    /// a sequence, such as those of single files, are printed unchanged.
+    ///
+    /// Sequences listed in the default pattern style don't show their padding, so list them with
+    /// `--pattern-style hash` or `printf` to expand to padded names.
+    lines: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+struct FramesOpt {
+    /// Frame sets to combine, such as `1-100`, `1-100x2` or `1,5,10-20`. Sets starting with a
+    /// negative frame, such as `-5-5`, go after `--`.
+    #[clap(required = true)]
+    sets: Vec<FrameSet>,
+
+    /// Keep only frames that are also in the given set. Applied before `--subtract`.
+    #[clap(long, value_name = "FRAMES", allow_hyphen_values = true)]
+    intersect: Vec<FrameSet>,
+
+    /// Remove the frames of the given set.
+    #[clap(long, value_name = "FRAMES", allow_hyphen_values = true)]
+    subtract: Vec<FrameSet>,
+
+    /// Print the number of frames instead of the set.
+    #[clap(long)]
+    count: bool,
+}
+
+#[derive(Args, Debug)]
+struct ExecOpt {
+    /// The sequence to run the command on, with `#` standing in for the number (e.g. `frame#.exr`).
+    spec: String,
+
+    /// Command to run, in which `{}` is replaced by the member's path, or appended if absent. With
+    /// `{pattern}`, `{start}` or `{end}` it runs once with the printf-style pattern and the first and
+    /// last numbers instead.
+    #[clap(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+    command: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+struct OpenOpt {
+    /// The sequence to open, with `#` standing in for the number (e.g. `frame#.exr`), or a file.
+    spec: String,
+}
+
+#[derive(Args, Debug)]
+struct StatOpt {
+    /// The sequence to describe, with `#` standing in for the number (e.g. `frame#.exr`).
+    spec: String,
+}
+
+#[derive(Args, Debug)]
+struct CheckOpt {
+    /// Directory whose sequences to check, or a single sequence with `#` standing in for the
+    /// number (e.g. `frame#.exr`). Subdirectories are checked down to `--depth`.
+    #[clap(default_value = ".")]
+    path: String,
+
+    /// Print the report as JSON following the schema in `schema/lsn.schema.json`.
+    #[clap(long)]
+    json: bool,
+
+    /// Suggest how to re-render the missing and empty frames of each sequence: its printf pattern
+    /// followed by the frames formatted for the given tool.
+    #[clap(long, value_enum, value_name = "TOOL")]
+    suggest: Option<Resubmit>,
 }
 
-#[derive(Clone, Debug)]
-pub struct FileGroup {
-    /// Range of numbers in files with the same root and extension.
-    pub range: Option<std::ops::Range<usize>>,
-    pub parent: Option<PathBuf>,
-    pub stem: OsString,
-    pub ext: OsString,
-    pub meta: Option<Meta>,
+/// Tool to format frames to re-render for, in `lsn check --suggest`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum Resubmit {
+    /// A `-frames 46,102-110` argument, as taken by many render submitters.
+    Frames,
+    /// A `Frames=46,102-110` line for a Deadline job info file.
+    Deadline,
+    /// Nuke `-F 46 -F 102-110` arguments.
+    Nuke,
 }
 
-impl FileGroup {
-    pub fn modified(&self) -> Option<SystemTime> {
-        self.meta.as_ref().and_then(|meta| meta.modified)
+impl Resubmit {
+    /// Formats a list of frames and ranges such as `46,102-110`.
+    fn format(self, frames: &str) -> String {
+        match self {
+            Resubmit::Frames => format!("-frames {}", frames),
+            Resubmit::Deadline => format!("Frames={}", frames),
+            Resubmit::Nuke => frames.split(',').map(|f| format!("-F {}", f)).collect::<Vec<_>>().join(" "),
+        }
     }
-    pub fn accessed(&self) -> Option<SystemTime> {
-        self.meta.as_ref().and_then(|meta| meta.accessed)
+}
+
+/// Exit status when some entries couldn't be read.
+const EXIT_PARTIAL: i32 = 1;
+/// Exit status of `lsn check` when problems were found.
+const EXIT_PROBLEMS: i32 = 1;
+/// Exit status of `lsn exists` when frames are missing.
+const EXIT_MISSING: i32 = 1;
+/// Exit status for usage errors and failures that prevent listing anything.
+const EXIT_FATAL: i32 = 2;
+
+/// Whether warnings are suppressed with `--quiet`.
+static QUIET: AtomicBool = AtomicBool::new(false);
+/// Set when an entry couldn't be read.
+static PARTIAL_FAILURE: AtomicBool = AtomicBool::new(false);
+
+/// Prints a warning unless `--quiet` was given.
+fn warn(msg: std::fmt::Arguments) {
+    if !QUIET.load(AtomicOrdering::Relaxed) {
+        eprintln!("lsn: warning: {}", msg);
     }
-    pub fn created(&self) -> Option<SystemTime> {
-        self.meta.as_ref().and_then(|meta| meta.created)
+}
+
+/// Reports an entry that couldn't be read, which makes lsn exit with `EXIT_PARTIAL`.
+fn walk_error(err: walkdir::Error) {
+    // Links back to a directory above them are expected with `-L` and only skipped.
+    if let (Some(path), Some(ancestor)) = (err.path(), err.loop_ancestor()) {
+        warn(format_args!("skipping '{}', a link back to '{}'", path.display(), ancestor.display()));
+        return;
     }
-    pub fn size(&self) -> Option<u64> {
-        self.meta.as_ref().map(|meta| meta.size)
+    PARTIAL_FAILURE.store(true, AtomicOrdering::Relaxed);
+    match (err.path(), err.io_error()) {
+        (Some(path), Some(io_err)) => eprintln!("lsn: cannot read '{}': {}", path.display(), io_err),
+        _ => eprintln!("lsn: {}", err),
     }
-    pub fn is_dir(&self) -> bool {
-        self.meta.as_ref().map(|meta| meta.is_dir).unwrap_or(false)
+}
+
+/// Reports a globbed path that couldn't be read, which makes lsn exit with `EXIT_PARTIAL`.
+fn glob_error(err: glob::GlobError) {
+    PARTIAL_FAILURE.store(true, AtomicOrdering::Relaxed);
+    eprintln!("lsn: cannot read '{}': {}", err.path().display(), err.error());
+}
+
+/// Prints a fatal error and exits with `EXIT_FATAL`.
+fn fatal(err: impl std::fmt::Display) -> ! {
+    eprintln!("lsn: {}", err);
+    std::process::exit(EXIT_FATAL);
+}
+
+/// Exits with `EXIT_PARTIAL` if any entry couldn't be read.
+fn exit_on_partial_failure() {
+    if PARTIAL_FAILURE.load(AtomicOrdering::Relaxed) {
+        std::process::exit(EXIT_PARTIAL);
     }
-    pub fn is_symlink(&self) -> bool {
-        self.meta.as_ref().map(|meta| meta.is_symlink).unwrap_or(false)
+}
+
+/// Converts a time style into a chrono format string.
+fn parse_time_style(s: &str) -> Result<String, String> {
+    match s {
+        "default" | "locale" => Ok("%b %_d %H:%M".to_string()),
+        "iso" => Ok("%m-%d %H:%M".to_string()),
+        "long-iso" => Ok("%Y-%m-%d %H:%M".to_string()),
+        "full-iso" => Ok("%Y-%m-%d %H:%M:%S%.9f %z".to_string()),
+        _ => match s.strip_prefix('+') {
+            Some(format) => {
+                let items = chrono::format::StrftimeItems::new(format);
+                if items.clone().any(|item| item == chrono::format::Item::Error) {
+                    Err(format!("invalid time format '{}'", format))
+                } else {
+                    Ok(format.to_string())
+                }
+            }
+            None => Err(format!("invalid time style '{}', expected default, iso, long-iso, full-iso or +FORMAT", s)),
+        },
     }
 }
 
-fn main() {
-    let cli = clap::Command::new("lsn");
-    let cli = Opt::augment_args(cli);
-    let matches = cli.get_matches();
-    let opt = Opt::from_arg_matches(&matches).unwrap();
+fn parse_fps(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(fps) if fps.is_finite() && fps > 0.0 => Ok(fps),
+        _ => Err(format!("invalid frame rate '{}'", s)),
+    }
+}
 
-    let glob_options = glob::MatchOptions {
-        case_sensitive: true,
-        require_literal_separator: true,
-        require_literal_leading_dot: false,
-    };
+fn parse_time(s: &str) -> Result<SystemTime, String> {
+    lsn::parse_time_spec(s, SystemTime::now())
+}
 
-    let entries = glob::glob_with(&opt.path, glob_options).unwrap();
+impl Opt {
+    /// Whether output is styled. Only text output ever is, so other formats stay machine
+    /// readable.
+    fn use_color(&self) -> bool {
+        if self.format != Format::Text || self.nocolor {
+            return false;
+        }
+        match self.color {
+            ColorWhen::Always => true,
+            ColorWhen::Never => false,
+            ColorWhen::Auto => colored::control::SHOULD_COLORIZE.should_colorize(),
+        }
+    }
 
-    let regex = lsn::build_regex();
+    /// Applies `style` to `text` if output is styled.
+    fn style(&self, text: &str, style: Style) -> String {
+        if self.use_color() {
+            format!("\x1b[{}m{}\x1b[0m", style.code(), text)
+        } else {
+            text.to_string()
+        }
+    }
 
-    let mut map: IndexMap<String, FileGroup> = IndexMap::new();
+    /// Colors `text`, shown for an entry with the given kind and name, if output is styled.
+    fn paint(&self, kind: Kind, name: &OsStr, text: &str) -> String {
+        if self.use_color() {
+            self.colors.paint(kind, name, text)
+        } else {
+            text.to_string()
+        }
+    }
 
-    for path in entries.filter_map(|e| e.ok()) {
-        for entry in WalkDir::new(path).max_depth(opt.depth).follow_links(opt.follow_links).into_iter().filter_map(|e| e.ok()) {
-            let path = entry.path();
-            let parent = path.parent().map(ToOwned::to_owned);
-            let stem = path.file_stem().map(ToOwned::to_owned).unwrap_or(OsString::from("."));
-            let stem_str = stem.to_string_lossy();
-            if !opt.all && stem_str.as_bytes()[0] == b'.' {
-                continue;
-            }
-            let extension = path.extension().map(ToOwned::to_owned).unwrap_or(OsString::from(""));
-            let mut ext = OsString::new();
-            if !extension.is_empty() {
-                ext.push(".");
-                ext.push(extension);
-            }
-            let file_name_str = path.file_name().map(|x| x.to_string_lossy().to_string()).unwrap_or("..".to_string()); // Used to create a key.
-            let Some(caps) = regex.captures(&file_name_str) else {
-                // Default range of size one will be treated as a single file and not a group anyways.
-                map.insert(file_name_str.to_string(), FileGroup { range: None, parent, stem, ext, meta: entry.metadata().ok().map(Meta::from) });
-                continue;
-            };
+    /// Formats a time padded to `width`, colored by its age if output is styled.
+    fn paint_time(&self, time: Option<SystemTime>, width: usize) -> String {
+        let text = self.format_time(time);
+        let pad = width.saturating_sub(lsn::display_width(&text));
+        match time.filter(|_| self.use_color()) {
+            Some(time) => format!("{}{:pad$}", self.colors.paint_age(time, SystemTime::now(), &text), ""),
+            None => format!("{}{:pad$}", text, ""),
+        }
+    }
 
-            let key = format!("{}#{}", &caps["stem"], &caps["ext"]);
-            let num = caps["num"].parse::<usize>().unwrap();
-            // println!("{}, {}, {}, {}", &key, &file_name_str, num, &caps["ext"]);
-            let meta = entry.metadata().ok().map(Meta::from);
-            map.entry(key.clone()).and_modify(
-                |grp| {
-                    // Update range
-                    let range = grp.range.as_mut().unwrap();
-                    // println!("key: {}; range: {}..{}", key, range.start, range.end);
-                    range.start = range.start.min(num);
-                    range.end = range.end.max(num+1);
-
-                    if let Some((grp_meta, meta)) = grp.meta.as_mut().zip(meta.as_ref()) {
-                        // Update last modified and last accessed metadata
-                        if let Some((grp_modified, cur_modified)) = grp_meta.modified.as_mut().zip(meta.modified) {
-                            *grp_modified = (*grp_modified).max(cur_modified);
-                        }
-                        if let Some((grp_accessed, cur_accessed)) = grp_meta.accessed.as_mut().zip(meta.accessed) {
-                            *grp_accessed = (*grp_accessed).max(cur_accessed);
-                        }
-                        if let Some((grp_created, cur_created)) = grp_meta.created.as_mut().zip(meta.created) {
-                            *grp_created = (*grp_created).min(cur_created);
-                        }
-                        grp_meta.size += meta.size;
-                    }
-                }
-            ).or_insert(FileGroup { range: Some(num..num+1), parent, stem: OsString::from(&caps["stem"]), ext: OsString::from(&caps["ext"]), meta });
+    /// Formats a size right-aligned to `width`, colored relative to `max` with `--size-heat` if
+    /// output is styled.
+    fn paint_size(&self, size: Option<u64>, max: u64, width: usize) -> String {
+        let text = size.map(|size| self.format_size(size)).unwrap_or_default();
+        let pad = width.saturating_sub(lsn::display_width(&text));
+        match size.filter(|_| self.size_heat && self.use_color() && max > 0) {
+            Some(size) => format!("{:pad$}{}", "", colors::paint_heat(size as f64 / max as f64, &text)),
+            None => format!("{:pad$}{}", "", text),
         }
     }
 
-    let mut vec: Vec<_> = map.into_values().collect();
-    
-    let option_names = vec![
-        "sort_by_modified",
-        "sort_by_size"
-    ];
-    let mut sort_options = option_names.iter().filter(|name| {
-        matches.get_flag(name)
-    }).map(|&name| {
-        (name, matches.index_of(name))
-    }).collect::<Vec<_>>();
-    sort_options.sort_by(|(_, i), (_, j)| {
-        i.cmp(j)
-    });
+    /// Formats a size in the unit given by `--size-unit`, or as plain bytes.
+    fn format_size(&self, bytes: u64) -> String {
+        self.size_unit.map_or_else(|| bytes.to_string(), |unit| unit.format(bytes))
+    }
 
-    if !opt.unsorted || !sort_options.is_empty() {
-        vec.sort_by(|a,b| {
-            let mut less = std::cmp::Ordering::Equal;
-            if let Some((a_meta, b_meta)) = a.meta.as_ref().zip(b.meta.as_ref()) {
-                for (option, _) in sort_options.iter() {
-                    match *option {
-                        "sort_by_modified" => {
-                            less = less.then(
-                                a_meta.modified.cmp(&b_meta.modified)
-                            );
-                        }
-                        "sort_by_size" => {
-                            less = less.then(
-                                a_meta.size.cmp(&b_meta.size)
-                            );
-                        }
-                        _ => {}
-                    }
-                }
-            }
-            if !opt.unsorted {
-                less = less.then(a.stem.cmp(&b.stem));
-                if let Some((a_range, b_range)) = a.range.as_ref().zip(b.range.as_ref()) {
-                    less = less.then(a_range.clone().cmp(b_range.clone()));
-                }
-                // println!("comparing {:?}{:?} to {:?}{:?}: {:?}", &a.stem, &a.ext, &b.stem, &b.ext, a.ext.cmp(&b.ext));
-                less = less.then(a.ext.cmp(&b.ext));
-            }
-            if opt.reverse {
-                less.reverse()
-            } else {
-                less
-            }
-        });
+    fn format_time(&self, time: Option<SystemTime>) -> String {
+        time.map(|time| DateTime::<Local>::from(time).format(&self.time_style).to_string()).unwrap_or_default()
     }
 
-    let max_size = vec.iter().map(|x| x.size().unwrap_or(0)).max().unwrap_or(0);
-    let length_for_size = if max_size > 0 {
-        max_size.ilog10() as usize + 1
-    } else {
-        1
-    };
+    /// Whether names are prefixed by their directory.
+    fn show_dirs(&self) -> bool {
+        self.depth > 1 || self.directory || self.absolute || self.relative_to.is_some()
+    }
 
-    for value in vec.iter() {
-        let mut path = if opt.depth > 1 {
-            value.parent.clone().unwrap_or_else(|| PathBuf::from("/"))
-        } else {
-            PathBuf::new()
+    /// Directory as shown in listings, made absolute or relative to `--relative-to` if requested.
+    fn display_dir(&self, dir: &Path) -> PathBuf {
+        if !self.absolute && self.relative_to.is_none() {
+            return dir.to_owned();
+        }
+        let absolute = |dir: &Path| {
+            let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+            std::path::absolute(dir).map(|dir| lsn::normalize_path(&dir)).unwrap_or_else(|_| dir.to_owned())
         };
-        if let Some(ref range) = value.range {
-            let mut file_name = value.stem.clone();
-            if range.end - range.start == 1 {
-                file_name.push(range.start.to_string());
-                file_name.push(&value.ext);
-            } else {
-                file_name.push("#");
-                file_name.push(&value.ext);
-                file_name.push(format!(" ({}..{})", range.start, range.end-1));
-            }
-            path.push(file_name);
+        match &self.relative_to {
+            Some(base) => lsn::relative_path(&absolute(dir), &absolute(base)),
+            None => absolute(dir),
+        }
+    }
+
+    /// A scanner grouping entries as set by the options.
+    fn scanner(&self, matcher: &Matcher) -> Scanner {
+        Scanner::new(matcher.clone())
+            .with_hidden(self.all)
+            .with_ignore_files(!self.no_ignore)
+            .with_classic(self.classic)
+            .with_group_dirs(self.group_dirs)
+            .with_dates(self.dates)
+            .with_versions(self.versions)
+            .with_wrap(self.wrap)
+            .with_split_padding(self.split_padding)
+    }
+
+    /// Whether entries need to be `stat`ed for their sizes, times, permissions or inodes, rather
+    /// than only their file types.
+    fn needs_metadata(&self) -> bool {
+        self.long
+            || self.inode
+            || self.stats
+            || self.du
+            || self.by_dir
+            || self.across_dirs_report
+            || self.log.is_some()
+            || self.dereference
+            || self.sort_by_modified
+            || self.sort_by_created
+            || self.sort_by_size
+            || self.sort.iter().any(|key| matches!(key.field, SortField::Size | SortField::Mtime | SortField::Created))
+            || (self.top.is_some() && self.top_by == TopBy::Size)
+            || self.owner.is_some()
+            || self.group.is_some()
+            || self.larger_than.is_some()
+            || self.smaller_than.is_some()
+            || self.newer_than.is_some()
+            || self.older_than.is_some()
+            || self.format != Format::Text
+            || self.indicator_style() == IndicatorStyle::Classify
+            || self.types.contains(&EntryType::Executable)
+            || (self.use_color() && self.colors.has(Kind::Executable))
+            || matches!(self.command, Some(Command::Check(_) | Command::Stat(_)))
+    }
+
+    fn indicator_style(&self) -> IndicatorStyle {
+        if self.classify {
+            IndicatorStyle::Classify
+        } else if self.slash {
+            IndicatorStyle::Slash
         } else {
-            let mut filename = value.stem.clone();
-            filename.push(&value.ext);
-            path.push(filename);
+            self.indicator_style.unwrap_or(if self.classic { IndicatorStyle::None } else { IndicatorStyle::FileType })
         }
-        if opt.long {
-            let time = value.modified().map(|time| DateTime::<Local>::from(time).format("%b %_d %H:%M").to_string()).unwrap_or(String::new());
-            let size = value.size().map(|size| size.to_string()).unwrap_or(String::new());
-            print!("{:>length_for_size$} {} ", size, time);
-        }
-        let path_str = path.to_string_lossy();
-        if opt.nocolor {
-            if value.is_dir() {
-                println!("{}/", path_str);
-            } else if value.is_symlink() {
-                println!("{}@", path_str);
-            } else {
-                println!("{}", path_str);
-            }
-        } else if value.is_dir() {
-            println!("{}/", path_str.blue());
-        } else if value.is_symlink() {
-            println!("{}@", path_str.magenta());
+    }
+
+    fn quoting_style(&self) -> QuotingStyle {
+        if self.quote_name {
+            QuotingStyle::C
+        } else if self.escape {
+            QuotingStyle::Escape
         } else {
-            println!("{}", path_str);
+            QuotingStyle::Literal
         }
     }
 }
+
+/// Which indicators are appended to names to show their file type.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+enum IndicatorStyle {
+    /// No indicators.
+    None,
+    /// `/` after directories.
+    Slash,
+    /// `/`, `@`, `|` and `=` after directories, symlinks, FIFOs and sockets.
+    #[default]
+    FileType,
+    /// Like `file-type`, with `*` after executables as well.
+    Classify,
+}
+
+impl IndicatorStyle {
+    fn indicator(self, kind: Kind) -> &'static str {
+        match (self, kind) {
+            (IndicatorStyle::None, _) => "",
+            (_, Kind::Dir) => "/",
+            (IndicatorStyle::Slash, _) => "",
+            (_, Kind::Symlink) => "@",
+            (_, Kind::Fifo) => "|",
+            (_, Kind::Socket) => "=",
+            (IndicatorStyle::Classify, Kind::Executable) => "*",
+            _ => "",
+        }
+    }
+}
+
+/// A timestamp of a group shown by `-l`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum TimeField {
+    /// Latest modification time of any member.
+    Modified,
+    /// Latest access time of any member.
+    Accessed,
+    /// Earliest creation time of any member.
+    Created,
+}
+
+impl TimeField {
+    fn get(self, grp: &FileGroup) -> Option<SystemTime> {
+        match self {
+            TimeField::Modified => grp.modified(),
+            TimeField::Accessed => grp.accessed(),
+            TimeField::Created => grp.created(),
+        }
+    }
+
+    /// Column heading for `--header`.
+    fn label(self) -> &'static str {
+        match self {
+            TimeField::Modified => "MODIFIED",
+            TimeField::Accessed => "ACCESSED",
+            TimeField::Created => "CREATED",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+enum Format {
+    /// One line per group.
+    #[default]
+    Text,
+    /// A Markdown table with the name, range, count, size and modification time of each group.
+    Markdown,
+    /// An HTML table with the same columns as `markdown`.
+    Html,
+    /// A JSON document with a `schema_version` and a `groups` array of one object per group.
+    Json,
+    /// One JSON object per group and line, printed as directories are scanned with `--stream`.
+    Ndjson,
+}
+
+/// When to color output.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+enum ColorWhen {
+    /// Color output to terminals, unless `NO_COLOR` is set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Style of parts of the output other than entry names, which are colored by kind.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Style {
+    Bold,
+    Good,
+    Bad,
+}
+
+impl Style {
+    /// SGR code of the style.
+    fn code(self) -> &'static str {
+        match self {
+            Style::Bold => "1",
+            Style::Good => "32",
+            Style::Bad => "31",
+        }
+    }
+}
+
+/// What `--top` ranks groups by.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum TopBy {
+    /// Total size of the files in a group.
+    Size,
+    /// Number of files in a group.
+    Count,
+}
+
+impl TopBy {
+    /// Sort key putting the largest groups first.
+    fn sort_key(self) -> SortKey {
+        let field = match self {
+            TopBy::Size => SortField::Size,
+            TopBy::Count => SortField::Count,
+        };
+        SortKey { field, desc: true }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum EntryType {
+    /// Regular files, grouped or not.
+    #[value(name = "f")]
+    File,
+    /// Directories.
+    #[value(name = "d")]
+    Dir,
+    /// Symbolic links.
+    #[value(name = "l")]
+    Symlink,
+    /// Regular files with any execute permission bit set, or sequences of them.
+    #[value(name = "x")]
+    Executable,
+    /// Collapsed numbered sequences.
+    Seq,
+    /// Entries that are not part of a sequence.
+    Single,
+}
+
+impl EntryType {
+    fn matches(&self, grp: &FileGroup) -> bool {
+        match self {
+            EntryType::File => !grp.is_dir() && !grp.is_symlink(),
+            EntryType::Dir => grp.is_dir(),
+            EntryType::Symlink => grp.is_symlink(),
+            EntryType::Executable => Kind::of_group(grp) == Kind::Executable,
+            EntryType::Seq => grp.is_sequence(),
+            EntryType::Single => !grp.is_sequence(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum SortField {
+    Name,
+    Size,
+    Mtime,
+    Created,
+    Ext,
+    Count,
+    Start,
+    Gaps,
+    Dir,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct SortKey {
+    field: SortField,
+    desc: bool,
+}
+
+impl std::str::FromStr for SortKey {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, desc) = match s.split_once(':') {
+            Some((name, "desc")) => (name, Some(true)),
+            Some((name, "asc")) => (name, Some(false)),
+            Some((_, order)) => return Err(format!("invalid sort order '{}', expected 'asc' or 'desc'", order)),
+            None => (s, None),
+        };
+        let field = match name {
+            "name" => SortField::Name,
+            "size" => SortField::Size,
+            "mtime" => SortField::Mtime,
+            "created" => SortField::Created,
+            "ext" => SortField::Ext,
+            "count" => SortField::Count,
+            "start" => SortField::Start,
+            "gaps" => SortField::Gaps,
+            "dir" => SortField::Dir,
+            _ => return Err(format!("invalid sort key '{}', expected one of name, size, mtime, created, ext, count, start, gaps, dir", name)),
+        };
+        // The most broken sequences come first unless asked otherwise.
+        let desc = desc.unwrap_or(field == SortField::Gaps);
+        Ok(SortKey { field, desc })
+    }
+}
+
+/// A group being sorted, with its values for the `gaps` key worked out once beforehand since
+/// each takes a scan of its members.
+struct SortEntry {
+    grp: FileGroup,
+    gaps: (usize, usize),
+}
+
+impl SortKey {
+    fn compare(&self, x: &SortEntry, y: &SortEntry) -> std::cmp::Ordering {
+        let (a, b) = (&x.grp, &y.grp);
+        let ord = match self.field {
+            SortField::Name => a.stem.cmp(&b.stem),
+            SortField::Size => a.size().cmp(&b.size()),
+            SortField::Mtime => a.modified().cmp(&b.modified()),
+            SortField::Created => a.created().cmp(&b.created()),
+            SortField::Ext => a.ext.cmp(&b.ext),
+            SortField::Count => a.count().cmp(&b.count()),
+            SortField::Start => a.range.as_ref().map(|r| r.start).cmp(&b.range.as_ref().map(|r| r.start)),
+            SortField::Gaps => x.gaps.cmp(&y.gaps),
+            SortField::Dir => a.parent.cmp(&b.parent),
+        };
+        if self.desc {
+            ord.reverse()
+        } else {
+            ord
+        }
+    }
+}
+
+/// Sorts `groups` with `compare`, working out their gap counts first if any of `keys` is `gaps`.
+fn sort_entries(groups: &mut Vec<FileGroup>, keys: &[SortKey], compare: impl FnMut(&SortEntry, &SortEntry) -> std::cmp::Ordering) {
+    let gaps = keys.iter().any(|key| key.field == SortField::Gaps);
+    let mut entries: Vec<SortEntry> = groups
+        .drain(..)
+        .map(|grp| SortEntry { gaps: if gaps { (grp.gaps().len(), grp.missing_count()) } else { (0, 0) }, grp })
+        .collect();
+    entries.sort_by(compare);
+    groups.extend(entries.into_iter().map(|entry| entry.grp));
+}
+
+/// Progress line shown on stderr once scanning has taken longer than a second.
+struct Progress {
+    enabled: bool,
+    start: Instant,
+    last_update: Instant,
+    scanned: usize,
+    shown: bool,
+}
+
+impl Progress {
+    const DELAY: Duration = Duration::from_secs(1);
+    const INTERVAL: Duration = Duration::from_millis(100);
+
+    fn new(enabled: bool) -> Self {
+        let now = Instant::now();
+        Progress { enabled, start: now, last_update: now, scanned: 0, shown: false }
+    }
+
+    fn tick(&mut self, path: &Path) {
+        self.scanned += 1;
+        // Avoid querying the clock for every entry.
+        if !self.enabled || !self.scanned.is_multiple_of(256) {
+            return;
+        }
+        let now = Instant::now();
+        if now - self.start < Self::DELAY || now - self.last_update < Self::INTERVAL {
+            return;
+        }
+        let dir = path.parent().unwrap_or(path);
+        eprint!("\r\x1b[Kscanned {} entries: {}", self.scanned, lsn::escape_os_str(dir.as_os_str()));
+        self.last_update = now;
+        self.shown = true;
+    }
+
+    /// Erases the progress line, if one was printed.
+    fn clear(&mut self) {
+        if self.shown {
+            eprint!("\r\x1b[K");
+            self.shown = false;
+        }
+    }
+}
+
+/// Directories reached while following links, which skips directories reached again through
+/// another link, so that links between sibling trees can't multiply the listing.
+#[derive(Default)]
+struct Visited {
+    /// First path of each directory by device and inode.
+    dirs: HashMap<(u64, u64), PathBuf>,
+    /// Whether the contents of each directory checked so far are listed.
+    kept: HashMap<PathBuf, bool>,
+}
+
+impl Visited {
+    /// Whether a walked entry is kept. Entries inside or of skipped directories aren't.
+    ///
+    /// Works for walks yielding contents first too, as the directories above an entry are checked
+    /// when first seen.
+    fn keeps(&mut self, entry: &walkdir::DirEntry) -> bool {
+        match entry.path().parent() {
+            _ if entry.file_type().is_dir() => self.keeps_dir(entry.path(), entry.depth()),
+            Some(parent) if entry.depth() > 0 => self.keeps_dir(parent, entry.depth() - 1),
+            _ => true,
+        }
+    }
+
+    fn keeps_dir(&mut self, dir: &Path, depth: usize) -> bool {
+        if let Some(&kept) = self.kept.get(dir) {
+            return kept;
+        }
+        let parent_kept = depth == 0 || dir.parent().is_none_or(|parent| self.keeps_dir(parent, depth - 1));
+        let kept = parent_kept && self.enter(dir);
+        self.kept.insert(dir.to_owned(), kept);
+        kept
+    }
+
+    /// Records a directory, returning false if it's a link to one that was already recorded.
+    fn enter(&mut self, dir: &Path) -> bool {
+        let Some(id) = dir_id(dir) else {
+            return true;
+        };
+        match self.dirs.get(&id) {
+            Some(first) if dir.is_symlink() => {
+                warn(format_args!("skipping '{}', a link to '{}' which is already listed", dir.display(), first.display()));
+                false
+            }
+            Some(_) => true,
+            None => {
+                self.dirs.insert(id, dir.to_owned());
+                true
+            }
+        }
+    }
+}
+
+/// Device and inode of the directory at `path`, following links.
+#[cfg(unix)]
+fn dir_id(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::metadata(path).ok().map(|meta| (meta.dev(), meta.ino()))
+}
+
+/// Directories aren't identified elsewhere, leaving loops to walkdir's own detection.
+#[cfg(not(unix))]
+fn dir_id(_path: &Path) -> Option<(u64, u64)> {
+    None
+}
+
+/// Warns about problems found while grouping, such as invalid patterns in `.lsnignore` files.
+fn warn_scan_errors(scanner: &Scanner) {
+    for err in scanner.take_errors() {
+        warn(format_args!("{}", err));
+    }
+}
+
+/// Warns that scanning stopped at `--max-entries`, which makes lsn exit with `EXIT_PARTIAL`.
+fn warn_max_entries(max: usize) {
+    PARTIAL_FAILURE.store(true, AtomicOrdering::Relaxed);
+    warn(format_args!("stopped scanning after {} entries, the limit set by --max-entries", max));
+}
+
+/// Entries scanned, groups formed and time spent in each phase of a listing for `--timing`.
+#[derive(Debug)]
+struct Timings {
+    /// When lsn started, roughly.
+    start: Instant,
+    entries: Cell<usize>,
+    groups: Cell<usize>,
+    stat: Cell<Duration>,
+    sort: Cell<Duration>,
+    print: Cell<Duration>,
+}
+
+impl Timings {
+    fn new() -> Self {
+        Timings { start: Instant::now(), entries: Cell::new(0), groups: Cell::new(0), stat: Cell::default(), sort: Cell::default(), print: Cell::default() }
+    }
+
+    /// Adds the time since `start` to a phase.
+    fn add(phase: &Cell<Duration>, start: Instant) {
+        phase.set(phase.get() + start.elapsed());
+    }
+
+    /// Prints the counts and times to stderr, given the time taken up to printing, which excludes
+    /// waiting for the pager to exit. Time not spent in another phase is counted as walking, which
+    /// includes grouping the entries.
+    fn report(&self, elapsed: Duration) {
+        let (stat, sort, print) = (self.stat.get(), self.sort.get(), self.print.get());
+        let walk = elapsed.saturating_sub(stat + sort + print);
+        eprintln!(
+            "lsn: {} entries, {} groups in {:.3?} (walk {:.3?}, stat {:.3?}, sort {:.3?}, print {:.3?})",
+            self.entries.get(),
+            self.groups.get(),
+            elapsed,
+            walk,
+            stat,
+            sort,
+            print
+        );
+    }
+}
+
+/// Recursive sizes of listed directories accumulated during the walk for `--du`.
+struct DiskUsage {
+    /// Deepest level of directories whose sizes are tracked.
+    max_depth: usize,
+    sizes: HashMap<PathBuf, u64>,
+    /// Newest modification time of the contents of each tracked directory.
+    modified: HashMap<PathBuf, SystemTime>,
+}
+
+impl DiskUsage {
+    fn new(max_depth: usize) -> Self {
+        DiskUsage { max_depth, sizes: HashMap::new(), modified: HashMap::new() }
+    }
+
+    /// Adds the size and modification time of a non-directory entry to all of its listed
+    /// ancestors.
+    fn add(&mut self, entry: &walkdir::DirEntry) {
+        if entry.file_type().is_dir() {
+            return;
+        }
+        let Some(meta) = entry.metadata().ok() else {
+            return;
+        };
+        let modified = meta.modified().ok();
+        let depth = entry.depth();
+        for (i, ancestor) in entry.path().ancestors().enumerate().take(depth + 1).skip(1) {
+            if depth - i <= self.max_depth {
+                *self.sizes.entry(ancestor.to_owned()).or_default() += meta.len();
+                if let Some(modified) = modified {
+                    let newest = self.modified.entry(ancestor.to_owned()).or_insert(modified);
+                    *newest = (*newest).max(modified);
+                }
+            }
+        }
+    }
+
+    /// Replaces the size of a directory group by the total size of its contents, and its
+    /// modification time by the newest of its contents if that is later.
+    fn apply(&self, grp: &mut FileGroup) {
+        if !grp.is_dir() {
+            return;
+        }
+        let parent = grp.parent.clone().unwrap_or_default();
+        let names = if grp.range.is_none() {
+            let mut name = grp.stem.clone();
+            name.push(&grp.ext);
+            vec![name]
+        } else {
+            grp.members.iter().map(|m| m.name.clone()).collect()
+        };
+        let paths: Vec<_> = names.iter().map(|name| parent.join(name)).collect();
+        let size = paths.iter().map(|path| self.sizes.get(path).copied().unwrap_or(0)).sum();
+        let modified = paths.iter().filter_map(|path| self.modified.get(path)).max().copied();
+        if let Some(meta) = grp.meta.as_mut() {
+            meta.size = size;
+            meta.modified = meta.modified.max(modified);
+        }
+    }
+}
+
+/// Metadata of a walked entry, or of its target with `--dereference` unless the link is broken.
+///
+/// Only the file type is read when no option needs sizes, times or permissions, saving a `stat`
+/// call per entry.
+fn entry_meta(entry: &walkdir::DirEntry, opt: &Opt) -> Option<Meta> {
+    if !opt.needs_metadata() {
+        return Some(Kind::of(entry.file_type()).meta());
+    }
+    if opt.dereference && entry.path_is_symlink() {
+        if let Ok(meta) = std::fs::metadata(entry.path()) {
+            let meta = Meta::from(meta);
+            return Some(Meta { shared: meta.shared || !opt.count_links, ..meta });
+        }
+    }
+    entry.metadata().ok().map(Meta::from)
+}
+
+/// Metadata of a cached entry of the given kind, read from the filesystem only if needed.
+fn cached_meta(path: &Path, kind: Kind, opt: &Opt) -> Option<Meta> {
+    if !opt.needs_metadata() {
+        return Some(kind.meta());
+    }
+    if opt.dereference && kind == Kind::Symlink {
+        if let Ok(meta) = std::fs::metadata(path) {
+            let meta = Meta::from(meta);
+            return Some(Meta { shared: meta.shared || !opt.count_links, ..meta });
+        }
+    }
+    let meta = if opt.follow_links { std::fs::metadata(path).or_else(|_| path.symlink_metadata()) } else { path.symlink_metadata() };
+    meta.ok().map(Meta::from)
+}
+
+/// Groups walked entries by stem and extension into `scanner`.
+fn group_entries(entries: impl Iterator<Item = walkdir::DirEntry>, opt: &Opt, scanner: &mut Scanner) {
+    for entry in entries {
+        let is_dir = entry.file_type().is_dir();
+        group_entry(entry.path(), entry.depth(), is_dir, || entry_meta(&entry, opt), opt, scanner);
+    }
+}
+
+/// Groups and prints entries that aren't read from the filesystem, such as those of an archive,
+/// given with their depth below the listed path.
+fn print_listed(entries: impl Iterator<Item = (PathBuf, usize, Meta)>, matcher: &Matcher, opt: &Opt) {
+    let mut scanner = opt.scanner(matcher);
+    for (path, depth, meta) in entries.filter(|(_, depth, _)| opt.directory || *depth > 0) {
+        opt.timings.entries.set(opt.timings.entries.get() + 1);
+        group_entry(&path, depth, meta.is_dir, || Some(meta), opt, &mut scanner);
+    }
+    let groups = prepare_groups(scanner, None, opt);
+    let mut out = Output::new(opt.paging);
+    print_groups(&groups, opt, &mut out);
+    let elapsed = opt.timings.start.elapsed();
+    out.finish();
+    if opt.timing {
+        opt.timings.report(elapsed);
+    }
+}
+
+/// Groups the entries of the directory `dir` from a cached snapshot into `scanner`.
+fn group_cached(dir: &Path, entries: Vec<(OsString, Kind)>, opt: &Opt, scanner: &mut Scanner) {
+    for (name, kind) in entries {
+        let path = dir.join(name);
+        group_entry(&path, 1, kind == Kind::Dir, || cached_meta(&path, kind, opt), opt, scanner);
+    }
+}
+
+/// Adds the entry at `path`, found `depth` levels below a listed path, to its group in `scanner`.
+/// Its metadata is only read if the entry isn't skipped.
+fn group_entry(path: &Path, depth: usize, is_dir: bool, meta: impl FnOnce() -> Option<Meta>, opt: &Opt, scanner: &mut Scanner) {
+    // Reading the clock around each `stat` call is only worth it when the time is reported.
+    let meta = || {
+        if !opt.timing {
+            return meta();
+        }
+        let start = Instant::now();
+        let meta = meta();
+        Timings::add(&opt.timings.stat, start);
+        meta
+    };
+    scanner.add_entry(path, depth, is_dir, meta);
+}
+
+/// Finds the sequence described by a spec like `path/to/frame#.exr`, where `#` stands in for the
+/// number.
+fn find_group(spec: &str, matcher: &Matcher, opt: &Opt) -> Result<FileGroup, String> {
+    find_sequence(spec, matcher, opt)?.ok_or_else(|| format!("no sequence matching '{}'", spec))
+}
+
+/// Like `find_group`, but returns `None` if no sequence matches.
+fn find_sequence(spec: &str, matcher: &Matcher, opt: &Opt) -> Result<Option<FileGroup>, String> {
+    let path = Path::new(spec);
+    let dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let pattern = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+    let (stem, ext) = match (pattern.find('#'), pattern.rfind('#')) {
+        (Some(first), Some(last)) => (&pattern[..first], &pattern[last + 1..]),
+        _ => return Err(format!("'{}' is missing a '#' placeholder for the number", spec)),
+    };
+    let mut scanner = opt.scanner(matcher).with_split_padding(false);
+    let walker = WalkDir::new(dir).min_depth(1).max_depth(1).follow_links(opt.follow_links);
+    group_entries(walker.into_iter().filter_map(|e| e.map_err(walk_error).ok()), opt, &mut scanner);
+    warn_scan_errors(&scanner);
+    // A spec without an extension, such as `frame#`, matches sequences with any extension.
+    let mut candidates: Vec<FileGroup> = scanner
+        .into_groups()
+        .into_iter()
+        .filter(|grp| grp.range.is_some() && grp.stem == stem && (grp.ext == ext || ext.is_empty()))
+        .collect();
+    if let Some(exact) = candidates.iter().position(|grp| grp.ext == ext) {
+        return Ok(Some(candidates.swap_remove(exact)));
+    }
+    match candidates.len() {
+        0 => Ok(None),
+        1 => Ok(Some(candidates.remove(0))),
+        _ => choose_group(spec, candidates, opt).map(Some),
+    }
+}
+
+/// Asks which of several sequences matching `spec` is meant, or fails listing them if input
+/// isn't interactive or `--no-interactive` is given.
+fn choose_group(spec: &str, mut candidates: Vec<FileGroup>, opt: &Opt) -> Result<FileGroup, String> {
+    candidates.sort_by(|a, b| a.ext.cmp(&b.ext));
+    let names: Vec<String> = candidates
+        .iter()
+        .map(|grp| {
+            let path = grp.parent.clone().unwrap_or_default().join(grp.name());
+            format!("{} ({} file{})", lsn::escape_os_str(path.as_os_str()), grp.count(), if grp.count() == 1 { "" } else { "s" })
+        })
+        .collect();
+    let stdin = std::io::stdin();
+    if opt.no_interactive || !stdin.is_terminal() || !std::io::stderr().is_terminal() {
+        return Err(format!("'{}' matches {} sequences, give one of:\n  {}", spec, names.len(), names.join("\n  ")));
+    }
+    eprintln!("'{}' matches {} sequences:", spec, names.len());
+    for (i, name) in names.iter().enumerate() {
+        eprintln!("  {}) {}", i + 1, name);
+    }
+    loop {
+        eprint!("Choose a sequence [1-{}]: ", names.len());
+        let mut line = String::new();
+        if stdin.read_line(&mut line).map_err(|e| format!("failed to read choice: {}", e))? == 0 {
+            return Err("no sequence chosen".to_string());
+        }
+        match line.trim().parse::<usize>() {
+            Ok(choice) if (1..=candidates.len()).contains(&choice) => return Ok(candidates.swap_remove(choice - 1)),
+            _ => eprintln!("invalid choice '{}'", line.trim()),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+}
+
+fn link(link_opt: &LinkOpt, matcher: &Matcher, opt: &Opt) -> Result<(), String> {
+    let grp = find_group(&link_opt.spec, matcher, opt)?;
+    let dest = &link_opt.dest;
+    let mut members = grp.members.clone();
+    members.sort_by(|a, b| a.number.cmp(&b.number).then_with(|| a.name.cmp(&b.name)));
+    let parent = grp.parent.clone().unwrap_or_default();
+    let start = link_opt.start.unwrap_or(members[0].number);
+    let padding = grp.padding();
+    // Members sharing a number, such as `f01` and `f1`, would both need the same new number.
+    if let Some((number, names)) = grp.duplicates().into_iter().next().filter(|_| link_opt.renumber) {
+        let names: Vec<_> = names.iter().map(|name| format!("'{}'", name.to_string_lossy())).collect();
+        return Err(format!("cannot renumber, frame {} is shared by {}", number, names.join(" and ")));
+    }
+    let mut numbers: Vec<isize> = members.iter().map(|m| m.number).collect();
+    numbers.dedup();
+
+    // Every link is checked before any is created, so that a failure leaves nothing half done.
+    let mut links: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut sources: HashMap<PathBuf, PathBuf> = HashMap::new();
+    for member in members.iter() {
+        let target = parent.join(&member.name);
+        let name = if link_opt.renumber {
+            let index = numbers.binary_search(&member.number).unwrap_or_default();
+            let mut name = grp.stem.clone();
+            name.push(grp.format_number(start + index as isize, padding));
+            name.push(&grp.ext);
+            name
+        } else {
+            member.name.clone()
+        };
+        let link = dest.join(name);
+        if let Some(other) = sources.insert(link.clone(), target.clone()) {
+            return Err(format!("both '{}' and '{}' would be linked as '{}'", other.display(), target.display(), link.display()));
+        }
+        links.push((target, link));
+    }
+    let mut create = Vec::new();
+    let mut existing = Vec::new();
+    let mut kept = 0;
+    for (target, link) in links {
+        let target = std::fs::canonicalize(&target).map_err(|e| format!("failed to resolve '{}': {}", target.display(), e))?;
+        match link.symlink_metadata() {
+            Err(_) => create.push((target, link)),
+            Ok(_) if std::fs::read_link(&link).is_ok_and(|current| current == target) => kept += 1,
+            Ok(meta) if meta.is_dir() => return Err(format!("'{}' is an existing directory", link.display())),
+            Ok(_) => existing.push((target, link)),
+        }
+    }
+    match link_opt.existing {
+        Existing::Fail if !existing.is_empty() => {
+            return Err(format!(
+                "{} link{} would replace existing files, e.g. '{}', use --existing skip or overwrite",
+                existing.len(),
+                if existing.len() == 1 { "" } else { "s" },
+                existing[0].1.display()
+            ));
+        }
+        Existing::Overwrite => create.append(&mut existing),
+        _ => {}
+    }
+    create.sort_by(|a, b| a.1.cmp(&b.1));
+
+    if link_opt.dry_run {
+        let preview: Vec<_> = create.into_iter().map(|(target, link)| (Some(target), link)).collect();
+        print_preview(&preview, "link", opt);
+        return Ok(());
+    }
+    std::fs::create_dir_all(dest).map_err(|e| format!("failed to create '{}': {}", dest.display(), e))?;
+    for (target, link) in create.iter() {
+        if link.symlink_metadata().is_ok() {
+            std::fs::remove_file(link).map_err(|e| format!("failed to replace '{}': {}", link.display(), e))?;
+        }
+        symlink(target, link).map_err(|e| format!("failed to create link '{}': {}", link.display(), e))?;
+    }
+    let skipped = kept + existing.len();
+    if skipped > 0 {
+        println!("Linked {} files into {}, skipped {} existing", create.len(), dest.display(), skipped);
+    } else {
+        println!("Linked {} files into {}", create.len(), dest.display());
+    }
+    Ok(())
+}
+
+/// Writes the members of a sequence into a tar archive under their paths relative to the current
+/// directory.
+fn tar(tar_opt: &TarOpt, matcher: &Matcher, opt: &Opt) -> Result<(), String> {
+    let grp = find_group(&tar_opt.spec, matcher, opt)?;
+    let output = &tar_opt.output;
+    let compress = tar_opt.zstd || output.extension().is_some_and(|ext| ext == "zst" || ext == "tzst");
+    let file = std::fs::File::create(output).map_err(|e| format!("failed to create '{}': {}", output.display(), e))?;
+    let write_err = |e: std::io::Error| format!("failed to write '{}': {}", output.display(), e);
+    let writer: Box<dyn Write> = if compress {
+        Box::new(zstd::Encoder::new(file, 0).map_err(write_err)?.auto_finish())
+    } else {
+        Box::new(file)
+    };
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(opt.follow_links);
+
+    let mut members = grp.members.clone();
+    members.sort_by_key(|m| m.number);
+    let parent = grp.parent.clone().unwrap_or_default();
+    for member in members.iter() {
+        let path = parent.join(&member.name);
+        // Archive paths are kept relative, without a root or leading `./`.
+        let name: PathBuf = path.components().filter(|c| matches!(c, std::path::Component::Normal(_))).collect();
+        builder
+            .append_path_with_name(&path, &name)
+            .map_err(|e| format!("failed to add '{}': {}", path.display(), e))?;
+    }
+    builder.into_inner().and_then(|mut w| w.flush()).map_err(write_err)?;
+    println!("Archived {} files into {}", members.len(), output.display());
+    Ok(())
+}
+
+/// Renames the members of a sequence so all numbers have the same zero padding.
+fn repad(repad_opt: &RepadOpt, matcher: &Matcher, opt: &Opt) -> Result<(), String> {
+    let grp = find_group(&repad_opt.spec, matcher, opt)?;
+    let width = repad_opt.width;
+    let plan = grp.rename_plan(|m| {
+        let mut name = grp.stem.clone();
+        name.push(grp.format_number(m.number, width));
+        name.push(&grp.ext);
+        name
+    })?;
+    let collisions = plan.collisions();
+    if let Some(first) = collisions.first() {
+        return Err(format!(
+            "renaming would overwrite {} existing file{}, e.g. '{}'",
+            collisions.len(),
+            if collisions.len() == 1 { "" } else { "s" },
+            first.display()
+        ));
+    }
+    if repad_opt.dry_run {
+        let preview: Vec<_> = plan.steps().iter().map(|step| (Some(step.from.clone()), step.to.clone())).collect();
+        print_preview(&preview, "rename", opt);
+        return Ok(());
+    }
+    plan.execute()?;
+    println!("Renamed {} files", plan.len());
+    Ok(())
+}
+
+/// Creates empty files for frames of a sequence that don't exist yet.
+fn touch(touch_opt: &TouchOpt, matcher: &Matcher, opt: &Opt) -> Result<(), String> {
+    let path = Path::new(&touch_opt.spec);
+    let pattern = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+    let (stem, hashes, ext) = match (pattern.find('#'), pattern.rfind('#')) {
+        (Some(first), Some(last)) => (&pattern[..first], last + 1 - first, &pattern[last + 1..]),
+        _ => return Err(format!("'{}' is missing a '#' placeholder for the number", touch_opt.spec)),
+    };
+    let dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    // A missing directory or sequence just means that no frames exist yet.
+    let existing = if dir.is_dir() { find_sequence(&touch_opt.spec, matcher, opt)? } else { None };
+    let width = match &existing {
+        _ if hashes > 1 => hashes,
+        Some(grp) => grp.pad_width(),
+        None => 0,
+    };
+    let present: BTreeSet<isize> = existing.as_ref().map(FileGroup::frames).unwrap_or_default().into_iter().collect();
+    let missing: Vec<isize> = touch_opt.frames.iter().filter(|n| !present.contains(n)).collect();
+    if !touch_opt.dry_run && !missing.is_empty() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("failed to create '{}': {}", dir.display(), e))?;
+    }
+    let mut preview = Vec::new();
+    for n in missing.iter() {
+        let sign = if *n < 0 { "-" } else { "" };
+        let file = path.with_file_name(format!("{}{}{:0width$}{}", stem, sign, n.unsigned_abs(), ext));
+        if touch_opt.dry_run {
+            preview.push((None, file));
+            continue;
+        }
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&file)
+            .map_err(|e| format!("failed to create '{}': {}", file.display(), e))?;
+    }
+    if touch_opt.dry_run {
+        print_preview(&preview, "create", opt);
+    } else {
+        let skipped = touch_opt.frames.len() - missing.len();
+        println!("Created {} files, skipped {} existing", missing.len(), skipped);
+    }
+    Ok(())
+}
+
+fn exists(exists_opt: &ExistsOpt, matcher: &Matcher, opt: &Opt) -> Result<(), String> {
+    let path = Path::new(&exists_opt.spec);
+    if !path.file_name().is_some_and(|name| name.to_string_lossy().contains('#')) {
+        return Err(format!("'{}' is missing a '#' placeholder for the number", exists_opt.spec));
+    }
+    let dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    // A missing directory just means that no frames exist.
+    let grp = if dir.is_dir() { find_sequence(&exists_opt.spec, matcher, opt)? } else { None };
+    let present: FrameSet = grp.as_ref().map(FileGroup::frames).unwrap_or_default().into_iter().collect();
+    let missing = exists_opt.frames.difference(&present);
+    if missing.is_empty() {
+        return Ok(());
+    }
+    if !exists_opt.quiet {
+        println!("{} of {} frames missing: {}", missing.len(), exists_opt.frames.len(), missing);
+    }
+    std::process::exit(EXIT_MISSING);
+}
+
+/// Prints the changes a subcommand would make with `--dry-run`, one `old → new` row per file with
+/// the new paths aligned, followed by a count such as `Would rename 3 files`. Rows without an
+/// old path are for files that would be created.
+fn print_preview(changes: &[(Option<PathBuf>, PathBuf)], verb: &str, opt: &Opt) {
+    let mut out = std::io::stdout().lock();
+    let old: Vec<String> = changes.iter().map(|(from, _)| from.as_ref().map(|from| lsn::escape_os_str(from.as_os_str())).unwrap_or_default()).collect();
+    let width = old.iter().map(|from| lsn::display_width(from)).max().unwrap_or(0);
+    for (from, (_, to)) in old.iter().zip(changes) {
+        let pad = width - lsn::display_width(from);
+        let to = opt.style(&lsn::escape_os_str(to.as_os_str()), Style::Good);
+        let result = match width {
+            0 => writeln!(out, "{}", to),
+            _ => writeln!(out, "{}{:pad$} → {}", opt.style(from, Style::Bad), "", to),
+        };
+        if result.is_err() {
+            return;
+        }
+    }
+    let files = if changes.len() == 1 { "file" } else { "files" };
+    let _ = writeln!(out, "Would {} {} {}", verb, changes.len(), files);
+}
+
+/// Prints the path of every frame of the sequences on the given lines of lsn output.
+fn expand(expand_opt: &ExpandOpt) -> Result<(), String> {
+    let mut out = std::io::stdout().lock();
+    let mut expand_line = |line: &str| match lsn::ListedSequence::parse(line) {
+        Some(seq) => seq.frames.iter().try_for_each(|n| writeln!(out, "{}", seq.path(n))),
+        None if line.trim().is_empty() => Ok(()),
+        None => writeln!(out, "{}", line),
+    };
+    let result = if expand_opt.lines.is_empty() {
+        std::io::stdin().lines().try_for_each(|line| expand_line(&line?))
+    } else {
+        expand_opt.lines.iter().try_for_each(|line| expand_line(line))
+    };
+    match result {
+        Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => Err(format!("failed to expand: {}", e)),
+        _ => Ok(()),
+    }
+}
+
+fn frames(frames_opt: &FramesOpt) -> Result<(), String> {
+    let union = frames_opt.sets.iter().fold(FrameSet::default(), |acc, set| acc.union(set));
+    let intersection = frames_opt.intersect.iter().fold(union, |acc, set| acc.intersection(set));
+    let result = frames_opt.subtract.iter().fold(intersection, |acc, set| acc.difference(set));
+    let written = if frames_opt.count { writeln!(std::io::stdout(), "{}", result.len()) } else { writeln!(std::io::stdout(), "{}", result) };
+    match written {
+        Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => Err(format!("failed to write frames: {}", e)),
+        _ => Ok(()),
+    }
+}
+
+/// Replaces placeholders such as `{}` in `args` by their values, returning whether any were found.
+fn expand_placeholders(args: &[String], values: &[(&str, &std::ffi::OsStr)]) -> (Vec<OsString>, bool) {
+    let mut found = false;
+    let args = args
+        .iter()
+        .map(|arg| {
+            let mut expanded = OsString::new();
+            let mut rest = arg.as_str();
+            while let Some((pos, placeholder, value)) = values
+                .iter()
+                .filter_map(|&(placeholder, value)| rest.find(placeholder).map(|pos| (pos, placeholder, value)))
+                .min_by_key(|&(pos, _, _)| pos)
+            {
+                found = true;
+                expanded.push(&rest[..pos]);
+                expanded.push(value);
+                rest = &rest[pos + placeholder.len()..];
+            }
+            expanded.push(rest);
+            expanded
+        })
+        .collect();
+    (args, found)
+}
+
+/// Runs a command for each member of a sequence, or once for the whole sequence if it uses any of
+/// the group placeholders.
+fn exec(exec_opt: &ExecOpt, matcher: &Matcher, opt: &Opt) -> Result<(), String> {
+    let grp = find_group(&exec_opt.spec, matcher, opt)?;
+    let (program, args) = exec_opt.command.split_first().unwrap();
+    let parent = grp.parent.clone().unwrap_or_default();
+    let (first, last) = grp.span().unwrap();
+    let pattern = parent.join(grp.printf_pattern());
+    let (start, end) = (grp.format_number(first, 0), grp.format_number(last, 0));
+    let group_values = [
+        ("{pattern}", pattern.as_os_str()),
+        ("{start}", start.as_ref()),
+        ("{end}", end.as_ref()),
+    ];
+    let run = |args: Vec<OsString>| match std::process::Command::new(program).args(args).status() {
+        Ok(status) if status.success() => Ok(true),
+        Ok(_) => Ok(false),
+        Err(e) => Err(format!("failed to run '{}': {}", program, e)),
+    };
+    if expand_placeholders(args, &group_values).1 {
+        let mut values = group_values.to_vec();
+        values.push(("{}", pattern.as_os_str()));
+        if !run(expand_placeholders(args, &values).0)? {
+            return Err(format!("'{}' failed", program));
+        }
+        return Ok(());
+    }
+    let mut members = grp.members.clone();
+    members.sort_by_key(|m| m.number);
+    let mut failed = 0;
+    for member in members.iter() {
+        let path = parent.join(&member.name);
+        let (mut member_args, substituted) = expand_placeholders(args, &[("{}", path.as_os_str())]);
+        if !substituted {
+            member_args.push(path.into_os_string());
+        }
+        if !run(member_args)? {
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(format!("'{}' failed for {} of {} files", program, failed, members.len()));
+    }
+    Ok(())
+}
+
+/// Opens a sequence or file with the command configured for its extension, or the system's default
+/// application.
+fn open(open_opt: &OpenOpt, matcher: &Matcher, opt: &Opt) -> Result<(), String> {
+    let (first, pattern, ext) = if Path::new(&open_opt.spec).file_name().is_some_and(|name| name.to_string_lossy().contains('#')) {
+        let grp = find_group(&open_opt.spec, matcher, opt)?;
+        let parent = grp.parent.clone().unwrap_or_default();
+        let first = parent.join(&grp.members.iter().min_by_key(|m| m.number).unwrap().name);
+        (first, parent.join(grp.printf_pattern()), grp.ext.clone())
+    } else {
+        let path = PathBuf::from(&open_opt.spec);
+        if !path.exists() {
+            return Err(format!("'{}' does not exist", open_opt.spec));
+        }
+        let ext = path.extension().map(|ext| ext.to_owned()).unwrap_or_default();
+        (path.clone(), path, ext)
+    };
+    let config = Config::load()?;
+    let command = config.open_command(&ext.to_string_lossy()).map(lsn::split_args).unwrap_or_else(|| {
+        let opener = if cfg!(target_os = "macos") {
+            "open"
+        } else if cfg!(windows) {
+            "explorer"
+        } else {
+            "xdg-open"
+        };
+        vec![opener.to_string()]
+    });
+    let Some((program, args)) = command.split_first() else {
+        return Err(format!("empty open command for '{}'", ext.to_string_lossy()));
+    };
+    let (mut args, substituted) = expand_placeholders(args, &[("{}", first.as_os_str()), ("{pattern}", pattern.as_os_str())]);
+    if !substituted {
+        args.push(first.into_os_string());
+    }
+    std::process::Command::new(program)
+        .args(&args)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("failed to run '{}': {}", program, e))
+}
+
+/// Problems found in a sequence by `lsn check`.
+#[derive(Debug, serde::Serialize)]
+struct SequenceReport {
+    /// Path of the sequence with `#` in place of the number.
+    pattern: String,
+    first: isize,
+    last: isize,
+    count: usize,
+    /// Missing numbers as inclusive `[start, end]` ranges.
+    gaps: Vec<(isize, isize)>,
+    missing: usize,
+    /// Zero padding widths used by members, where 0 means unpadded, if there is more than one.
+    mixed_padding: Vec<usize>,
+    /// Names of members with no data, ordered by number and then name.
+    empty: Vec<String>,
+    /// Numbers of the members with no data.
+    #[serde(skip)]
+    empty_frames: BTreeSet<isize>,
+    duplicates: Vec<Duplicate>,
+    /// How to re-render the missing and empty frames, with `--suggest`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resubmit: Option<Suggestion>,
+}
+
+/// Missing frames of a sequence formatted for a render tool.
+#[derive(Debug, serde::Serialize)]
+struct Suggestion {
+    /// Path of the sequence with a printf conversion in place of the number.
+    pattern: String,
+    frames: String,
+}
+
+/// A number shared by more than one member of a sequence.
+#[derive(Debug, serde::Serialize)]
+struct Duplicate {
+    number: isize,
+    names: Vec<String>,
+}
+
+impl SequenceReport {
+    fn new(grp: &FileGroup, opt: &Opt) -> Self {
+        let mut path = grp.parent.as_deref().map(|dir| opt.display_dir(dir)).unwrap_or_default();
+        path.push(grp.name());
+        let (first, last) = grp.span().unwrap_or_default();
+        let gaps = grp.gaps();
+        let mut pads = grp.paddings();
+        if pads.len() < 2 {
+            pads.clear();
+        }
+        // Members are found in filesystem order, which differs between runs and machines.
+        let mut members: Vec<&Member> = grp.members.iter().collect();
+        members.sort_by(|a, b| a.number.cmp(&b.number).then_with(|| a.name.cmp(&b.name)));
+        let empty: Vec<&Member> = members.into_iter().filter(|m| m.meta.as_ref().is_some_and(|meta| !meta.is_dir && meta.size == 0)).collect();
+        SequenceReport {
+            pattern: lsn::escape_os_str(path.as_os_str()),
+            first,
+            last,
+            count: grp.members.len(),
+            missing: grp.missing_count(),
+            gaps,
+            mixed_padding: pads,
+            empty: empty.iter().map(|m| lsn::escape_os_str(&m.name)).collect(),
+            empty_frames: empty.iter().map(|m| m.number).collect(),
+            duplicates: grp
+                .duplicates()
+                .into_iter()
+                .map(|(number, names)| Duplicate { number, names: names.into_iter().map(|name| lsn::escape_os_str(name)).collect() })
+                .collect(),
+            resubmit: None,
+        }
+    }
+
+    fn is_ok(&self) -> bool {
+        self.gaps.is_empty() && self.mixed_padding.is_empty() && self.empty.is_empty() && self.duplicates.is_empty()
+    }
+}
+
+/// Result of `lsn check`.
+#[derive(Debug, serde::Serialize)]
+struct CheckReport {
+    schema_version: u32,
+    /// Number of sequences with problems.
+    problems: usize,
+    sequences: Vec<SequenceReport>,
+}
+
+/// Describes zero padding widths, e.g. `unpadded, 4 digits`.
+fn describe_paddings(pads: &[usize]) -> String {
+    let pads: Vec<_> = pads.iter().map(|&pad| if pad == 0 { "unpadded".to_string() } else { format!("{} digits", pad) }).collect();
+    pads.join(", ")
+}
+
+/// Prints a summary of a single sequence: its range and gaps, padding, sizes and times.
+fn stat(stat_opt: &StatOpt, matcher: &Matcher, opt: &Opt) -> Result<(), String> {
+    let grp = find_group(&stat_opt.spec, matcher, opt)?;
+    let (first, last) = grp.span().unwrap_or_default();
+    let dir = grp.parent.as_deref().map(|dir| opt.display_dir(dir)).unwrap_or_default();
+    let dir = if dir.as_os_str().is_empty() { PathBuf::from(".") } else { dir };
+    let mut rows = vec![
+        ("Sequence", lsn::escape_os_str(&grp.name())),
+        ("Directory", lsn::escape_os_str(dir.as_os_str())),
+        ("Range", format!("{}..{} ({} numbers)", grp.format_number(first, 0), grp.format_number(last, 0), grp.span_len())),
+        ("Count", grp.members.len().to_string()),
+    ];
+    let gaps = grp.gaps();
+    if gaps.is_empty() {
+        rows.push(("Missing", "none".to_string()));
+    } else {
+        rows.push(("Missing", format!("{} ({})", grp.missing_count(), grp.format_gaps(&gaps))));
+    }
+    let pads = grp.paddings();
+    let padding = match pads[..] {
+        [0] => "none".to_string(),
+        [pad] => format!("{} digits", pad),
+        _ => format!("mixed ({})", describe_paddings(&pads)),
+    };
+    rows.push(("Padding", padding));
+    let size = grp.size().unwrap_or(0);
+    rows.push(("Size", format!("{} ({} bytes)", lsn::format_size(size), lsn::format_thousands(size))));
+    if let Some(stats) = grp.size_stats() {
+        rows.push(("Sizes", format!("min {}, max {}", lsn::format_size(stats.min), lsn::format_size(stats.max))));
+    }
+    let times: Vec<_> = grp.members.iter().filter_map(|m| m.meta.as_ref()?.modified).collect();
+    if let (Some(oldest), Some(newest)) = (times.iter().min(), times.iter().max()) {
+        rows.push(("Oldest", opt.format_time(Some(*oldest))));
+        rows.push(("Newest", opt.format_time(Some(*newest))));
+    }
+    for (label, value) in rows {
+        println!("{:<10} {}", format!("{}:", label), value);
+    }
+    Ok(())
+}
+
+/// Checks the sequences in a directory, or a single sequence, printing a report. Exits with
+/// status 1 if problems were found.
+fn check(check_opt: &CheckOpt, matcher: &Matcher, opt: &Opt) -> Result<(), String> {
+    let mut groups = if Path::new(&check_opt.path).file_name().is_some_and(|name| name.to_string_lossy().contains('#')) {
+        vec![find_group(&check_opt.path, matcher, opt)?]
+    } else {
+        let path = Path::new(&check_opt.path);
+        if !path.is_dir() {
+            return Err(format!("'{}' is not a directory", path.display()));
+        }
+        // Sequences are checked as a whole, whatever their padding.
+        let mut scanner = opt.scanner(matcher).with_split_padding(false);
+        let walker = WalkDir::new(path)
+            .min_depth(1)
+            .max_depth(opt.depth)
+            .follow_links(opt.follow_links)
+            .same_file_system(opt.one_file_system);
+        group_entries(walker.into_iter().filter_map(|e| e.map_err(walk_error).ok()), opt, &mut scanner);
+        warn_scan_errors(&scanner);
+        scanner.into_groups().into_iter().filter(FileGroup::is_sequence).collect::<Vec<_>>()
+    };
+    groups.sort_by(|a, b| a.parent.cmp(&b.parent).then_with(|| a.name().cmp(&b.name())));
+    let mut sequences: Vec<_> = groups.iter().map(|grp| SequenceReport::new(grp, opt)).collect();
+    if let Some(tool) = check_opt.suggest {
+        for (seq, grp) in sequences.iter_mut().zip(groups.iter()) {
+            // Empty frames are as much in need of a re-render as missing ones.
+            let runs = lsn::merge_ranges(seq.gaps.iter().copied().chain(seq.empty_frames.iter().map(|&n| (n, n))));
+            if runs.is_empty() {
+                continue;
+            }
+            let mut pattern = grp.parent.as_deref().map(|dir| opt.display_dir(dir)).unwrap_or_default();
+            pattern.push(grp.printf_pattern());
+            seq.resubmit = Some(Suggestion { pattern: lsn::escape_os_str(pattern.as_os_str()), frames: tool.format(&grp.format_gaps(&runs)) });
+        }
+    }
+    let report = CheckReport { schema_version: SCHEMA_VERSION, problems: sequences.iter().filter(|seq| !seq.is_ok()).count(), sequences };
+
+    if check_opt.json {
+        let json = serde_json::to_string_pretty(&report).map_err(|e| format!("failed to write report: {}", e))?;
+        println!("{}", json);
+    } else {
+        match write_check_report(&report, &groups, &mut std::io::stdout().lock()) {
+            Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(format!("failed to write report: {}", e)),
+            _ => {}
+        }
+    }
+    if report.problems > 0 {
+        std::process::exit(EXIT_PROBLEMS);
+    }
+    Ok(())
+}
+
+/// Writes the text form of a `lsn check` report, given the groups its sequences are of.
+fn write_check_report(report: &CheckReport, groups: &[FileGroup], out: &mut impl Write) -> std::io::Result<()> {
+    for (seq, grp) in report.sequences.iter().zip(groups.iter()) {
+        let number = |n: isize| grp.format_number(n, 0);
+        writeln!(out, "{} ({}..{}): {}", seq.pattern, number(seq.first), number(seq.last), if seq.is_ok() { "ok" } else { "problems" })?;
+        if !seq.gaps.is_empty() {
+            writeln!(out, "  {} missing: {}", seq.missing, grp.format_gaps(&seq.gaps))?;
+        }
+        if let Some(resubmit) = &seq.resubmit {
+            writeln!(out, "  resubmit: {} {}", resubmit.pattern, resubmit.frames)?;
+        }
+        if !seq.mixed_padding.is_empty() {
+            writeln!(out, "  mixed padding: {}", describe_paddings(&seq.mixed_padding))?;
+        }
+        if !seq.empty.is_empty() {
+            writeln!(out, "  {} empty: {}", seq.empty.len(), seq.empty.join(", "))?;
+        }
+        for dup in seq.duplicates.iter() {
+            writeln!(out, "  duplicate {}: {}", number(dup.number), dup.names.join(", "))?;
+        }
+    }
+    writeln!(
+        out,
+        "{} sequence{} checked, {} with problems",
+        report.sequences.len(),
+        if report.sequences.len() == 1 { "" } else { "s" },
+        report.problems
+    )
+}
+
+/// Mount point of the filesystem that `path` is on: its highest ancestor on the same device.
+fn mount_point(path: &Path) -> Option<PathBuf> {
+    let device = |path: &Path| std::fs::metadata(path).ok().map(Meta::from).and_then(|meta| meta.device());
+    let path = std::fs::canonicalize(path).ok()?;
+    let dev = device(&path)?;
+    path.ancestors().take_while(|a| device(a) == Some(dev)).last().map(ToOwned::to_owned)
+}
+
+/// Turns scanned groups into the final listing: checks padding, applies `--du` sizes, filters and
+/// sorts.
+fn prepare_groups(scanner: Scanner, du: Option<&DiskUsage>, opt: &Opt) -> Vec<FileGroup> {
+    let start = Instant::now();
+    warn_scan_errors(&scanner);
+    let mut groups = scanner.into_groups();
+    if let Some(separators) = &opt.separators {
+        groups = merge_separators(groups, separators);
+    }
+    for grp in groups.iter() {
+        warn_duplicates(grp, opt);
+    }
+    warn_case_collisions(&groups);
+    if let Some(du) = du {
+        groups.iter_mut().for_each(|grp| du.apply(grp));
+    }
+    if opt.merge_ext {
+        groups = merge_extensions(groups);
+    }
+    filter_groups(&mut groups, opt);
+    if opt.latest {
+        for grp in groups.iter_mut().filter(|grp| grp.is_sequence()) {
+            let latest = grp.latest().unwrap().number;
+            grp.retain_members(|m| m.number == latest);
+        }
+    }
+    if let Some(top) = opt.top {
+        let key = opt.top_by.sort_key();
+        sort_entries(&mut groups, &[key], |a, b| key.compare(a, b));
+        groups.truncate(top);
+    }
+    sort_groups(&mut groups, opt);
+    Timings::add(&opt.timings.sort, start);
+    opt.timings.groups.set(opt.timings.groups.get() + groups.len());
+    groups
+}
+
+/// Merges sequences that differ only in their extension into one group.
+fn merge_extensions(groups: Vec<FileGroup>) -> Vec<FileGroup> {
+    let mut merged: IndexMap<(Option<PathBuf>, OsString, Option<usize>), FileGroup> = IndexMap::new();
+    let mut rest = Vec::new();
+    for grp in groups {
+        if !grp.is_sequence() || grp.is_dir() {
+            rest.push(grp);
+            continue;
+        }
+        match merged.entry((grp.parent.clone(), grp.stem.clone(), grp.pad)) {
+            indexmap::map::Entry::Occupied(mut entry) => entry.get_mut().merge(grp),
+            indexmap::map::Entry::Vacant(entry) => {
+                entry.insert(grp);
+            }
+        }
+    }
+    rest.extend(merged.into_values());
+    rest
+}
+
+/// Merges numbered groups whose stems differ only in trailing characters from `separators`, such
+/// as `frame.#.exr` and `frame_#.exr`, into one group.
+fn merge_separators(groups: Vec<FileGroup>, separators: &str) -> Vec<FileGroup> {
+    let mut merged: IndexMap<(Option<PathBuf>, OsString, OsString, Option<usize>), FileGroup> = IndexMap::new();
+    let mut rest = Vec::new();
+    for grp in groups {
+        if grp.range.is_none() || grp.is_dir() || grp.dated {
+            rest.push(grp);
+            continue;
+        }
+        let mut stem = grp.stem.as_encoded_bytes();
+        while let Some(sep) = separators.chars().find(|sep| stem.ends_with(sep.encode_utf8(&mut [0; 4]).as_bytes())) {
+            stem = &stem[..stem.len() - sep.len_utf8()];
+        }
+        let key = (grp.parent.clone(), lsn::os_string_from_bytes(stem), grp.ext.clone(), grp.pad);
+        match merged.entry(key) {
+            indexmap::map::Entry::Occupied(mut entry) => entry.get_mut().merge_stem(grp),
+            indexmap::map::Entry::Vacant(entry) => {
+                entry.insert(grp);
+            }
+        }
+    }
+    rest.extend(merged.into_values());
+    rest
+}
+
+/// Warns about members that have the same number but different names, such as different zero
+/// padding.
+fn warn_duplicates(grp: &FileGroup, opt: &Opt) {
+    let duplicates = grp.duplicates();
+    let Some((number, names)) = duplicates.first() else {
+        return;
+    };
+    let mut pattern = grp.parent.clone().unwrap_or_default();
+    pattern.push(grp.name());
+    let names: Vec<_> = names.iter().map(|name| lsn::escape_os_str(name)).collect();
+    let padded = grp.stems.is_empty() && duplicates.iter().any(|(_, names)| {
+        names.iter().any(|name| name.as_encoded_bytes().get(grp.stem.len()) == Some(&b'0'))
+    });
+    warn(format_args!(
+        "{}: {} frame number{} used by more than one file (e.g. {} as {}){}",
+        lsn::escape_os_str(pattern.as_os_str()),
+        duplicates.len(),
+        if duplicates.len() == 1 { "" } else { "s" },
+        grp.format_number(*number, 0),
+        names.join(" and "),
+        if padded && !opt.split_padding { ", use --split-padding to list them separately" } else { "" },
+    ));
+}
+
+/// Warns about names that differ only in case, which would collide on case-insensitive
+/// filesystems.
+fn warn_case_collisions(groups: &[FileGroup]) {
+    let mut seen: HashMap<(Option<&Path>, Vec<u8>), &OsString> = HashMap::new();
+    let mut collisions = Vec::new();
+    for grp in groups.iter() {
+        for member in grp.members.iter() {
+            let key = (grp.parent.as_deref(), member.name.as_encoded_bytes().to_ascii_lowercase());
+            match seen.get(&key) {
+                Some(&name) if *name != member.name => collisions.push((grp.parent.as_deref(), name, &member.name)),
+                Some(_) => {}
+                None => {
+                    seen.insert(key, &member.name);
+                }
+            }
+        }
+    }
+    if let Some(&(parent, first, second)) = collisions.first() {
+        let parent = parent.unwrap_or(Path::new(""));
+        warn(format_args!(
+            "{} name{} would collide on case-insensitive filesystems (e.g. {} and {})",
+            collisions.len(),
+            if collisions.len() == 1 { "" } else { "s" },
+            lsn::escape_os_str(parent.join(first).as_os_str()),
+            lsn::escape_os_str(parent.join(second).as_os_str()),
+        ));
+    }
+}
+
+fn filter_groups(groups: &mut Vec<FileGroup>, opt: &Opt) {
+    if !opt.types.is_empty() {
+        groups.retain(|grp| opt.types.iter().any(|ty| ty.matches(grp)));
+    }
+    if opt.name.is_some() || opt.regex.is_some() {
+        groups.retain(|grp| {
+            let stem = grp.bare_stem();
+            opt.name.as_ref().is_none_or(|glob| glob.matches(&String::from_utf8_lossy(stem)))
+                && opt.regex.as_ref().is_none_or(|regex| regex.is_match(stem))
+        });
+    }
+    if !opt.all && !opt.hide_stem.is_empty() {
+        groups.retain(|grp| {
+            let stem = String::from_utf8_lossy(grp.bare_stem());
+            !opt.hide_stem.iter().any(|glob| glob.matches(&stem))
+        });
+    }
+
+    // Filter on group aggregates.
+    groups.retain(|grp| {
+        let size = grp.size();
+        let modified = grp.modified();
+        opt.larger_than.is_none_or(|min| size.is_some_and(|size| size > min))
+            && opt.smaller_than.is_none_or(|max| size.is_some_and(|size| size < max))
+            && opt.newer_than.is_none_or(|t| modified.is_some_and(|m| m > t))
+            && opt.older_than.is_none_or(|t| modified.is_some_and(|m| m < t))
+            && opt.owner.is_none_or(|uid| grp.uid() == Some(uid))
+            && opt.group.is_none_or(|gid| grp.gid() == Some(gid))
+    });
+}
+
+fn sort_groups(groups: &mut Vec<FileGroup>, opt: &Opt) {
+    let mut sort_keys = opt.sort.clone();
+    if opt.sort_by_modified {
+        sort_keys.push(SortKey { field: SortField::Mtime, desc: false });
+    }
+    if opt.sort_by_created {
+        sort_keys.push(SortKey { field: SortField::Created, desc: false });
+    }
+    if opt.sort_by_size {
+        sort_keys.push(SortKey { field: SortField::Size, desc: false });
+    }
+    if sort_keys.is_empty() && opt.top.is_some() {
+        sort_keys.push(opt.top_by.sort_key());
+    }
+
+    if !opt.unsorted || !sort_keys.is_empty() || opt.sequences_first {
+        sort_entries(groups, &sort_keys, |x, y| {
+            let (a, b) = (&x.grp, &y.grp);
+            let mut less = std::cmp::Ordering::Equal;
+            for key in sort_keys.iter() {
+                less = less.then_with(|| key.compare(x, y));
+            }
+            if !opt.unsorted {
+                less = less.then(a.stem.cmp(&b.stem));
+                if let Some((a_range, b_range)) = a.range.as_ref().zip(b.range.as_ref()) {
+                    less = less.then(a_range.clone().cmp(b_range.clone()));
+                }
+                // println!("comparing {:?}{:?} to {:?}{:?}: {:?}", &a.stem, &a.ext, &b.stem, &b.ext, a.ext.cmp(&b.ext));
+                less = less.then(a.ext.cmp(&b.ext));
+                if opt.depth > 1 {
+                    less = less.then_with(|| a.parent.cmp(&b.parent));
+                }
+            }
+            if opt.reverse {
+                less = less.reverse();
+            }
+            if opt.sequences_first {
+                less = b.is_sequence().cmp(&a.is_sequence()).then(less);
+            }
+            less
+        });
+    }
+}
+
+/// Prints one summary row per directory containing any of `groups`.
+fn print_by_dir(groups: &[FileGroup], opt: &Opt, out: &mut Output) {
+    struct DirSummary<'a> {
+        dir: &'a Path,
+        groups: usize,
+        files: usize,
+        size: u64,
+        modified: Option<SystemTime>,
+    }
+    let mut dirs: IndexMap<&Path, DirSummary> = IndexMap::new();
+    for grp in groups {
+        let dir = grp.parent.as_deref().unwrap_or(Path::new(""));
+        let summary = dirs.entry(dir).or_insert(DirSummary { dir, groups: 0, files: 0, size: 0, modified: None });
+        summary.groups += 1;
+        summary.files += grp.count();
+        summary.size += grp.size().unwrap_or(0);
+        summary.modified = summary.modified.max(grp.modified());
+    }
+    let mut dirs: Vec<_> = dirs.into_values().collect();
+    dirs.sort_by(|a, b| {
+        let mut less = std::cmp::Ordering::Equal;
+        for key in opt.sort.iter() {
+            let ord = match key.field {
+                SortField::Size => a.size.cmp(&b.size),
+                SortField::Mtime => a.modified.cmp(&b.modified),
+                SortField::Count => a.files.cmp(&b.files),
+                _ => a.dir.cmp(b.dir),
+            };
+            less = less.then(if key.desc { ord.reverse() } else { ord });
+        }
+        if opt.sort_by_modified {
+            less = less.then(a.modified.cmp(&b.modified));
+        }
+        if opt.sort_by_size {
+            less = less.then(a.size.cmp(&b.size));
+        }
+        less = less.then(a.dir.cmp(b.dir));
+        if opt.reverse {
+            less.reverse()
+        } else {
+            less
+        }
+    });
+
+    let length_for_size = dirs.iter().map(|d| opt.format_size(d.size).len()).max().unwrap_or(1);
+    let max_size = dirs.iter().map(|d| d.size).max().unwrap_or(0);
+    let quoting = opt.quoting_style();
+    let (length_for_size, length_for_time) = if opt.header && !dirs.is_empty() {
+        let length_for_time = dirs.iter().map(|d| lsn::display_width(&opt.format_time(d.modified))).max().unwrap_or(0).max(8);
+        let (size, time) = (length_for_size.max(4), length_for_time);
+        let _ = writeln!(out, "{:>size$} {:<time$} DIRECTORY", "SIZE", "MODIFIED");
+        (size, time)
+    } else {
+        (length_for_size, 0)
+    };
+    for summary in dirs {
+        let time = opt.paint_time(summary.modified, length_for_time);
+        let dir = opt.display_dir(summary.dir);
+        let dir = if dir.as_os_str().is_empty() { PathBuf::from(".") } else { dir };
+        let dir = lsn::quote_os_str(dir.as_os_str(), quoting);
+        let dir = opt.paint(Kind::Dir, summary.dir.as_os_str(), &dir);
+        let _ = writeln!(
+            out,
+            "{} {} {}/ ({} group{}, {} file{})",
+            opt.paint_size(Some(summary.size), max_size, length_for_size),
+            time,
+            dir,
+            summary.groups,
+            if summary.groups == 1 { "" } else { "s" },
+            summary.files,
+            if summary.files == 1 { "" } else { "s" },
+        );
+    }
+}
+
+/// Prints one line per directory containing files among `groups`, with the number of sequences
+/// and files of each extension, most files first.
+fn print_ext_summary(groups: &[FileGroup], opt: &Opt, out: &mut Output) {
+    #[derive(Default)]
+    struct ExtCount {
+        sequences: usize,
+        files: usize,
+    }
+    let mut dirs: IndexMap<&Path, IndexMap<String, ExtCount>> = IndexMap::new();
+    for grp in groups.iter().filter(|grp| !grp.is_dir()) {
+        let dir = grp.parent.as_deref().unwrap_or(Path::new(""));
+        let ext = grp.ext.to_string_lossy();
+        let ext = if ext.is_empty() { "(none)".to_string() } else { ext.trim_start_matches('.').to_string() };
+        let count = dirs.entry(dir).or_default().entry(ext).or_default();
+        count.sequences += usize::from(grp.is_sequence());
+        count.files += grp.count();
+    }
+    dirs.sort_keys();
+    let plural = |n: usize, one: &str, many: &str| format!("{} {}", lsn::format_thousands(n as u64), if n == 1 { one } else { many });
+    for (dir, mut exts) in dirs {
+        exts.sort_by(|a_ext, a, b_ext, b| b.files.cmp(&a.files).then_with(|| a_ext.cmp(b_ext)));
+        let counts: Vec<String> = exts
+            .iter()
+            .map(|(ext, count)| match count.sequences {
+                0 => format!("{}: {}", ext, plural(count.files, "file", "files")),
+                n => format!("{}: {}/{}", ext, plural(n, "seq", "seqs"), plural(count.files, "file", "files")),
+            })
+            .collect();
+        let shown = opt.display_dir(dir);
+        let shown = if shown.as_os_str().is_empty() { PathBuf::from(".") } else { shown };
+        let shown = opt.paint(Kind::Dir, dir.as_os_str(), &lsn::quote_os_str(shown.as_os_str(), opt.quoting_style()));
+        let _ = writeln!(out, "{}/: {}", shown, counts.join(", "));
+    }
+}
+
+/// Prints each sequence name found in several directories followed by one line per directory
+/// with its variant, in order of directory.
+fn print_across_dirs(groups: &[FileGroup], opt: &Opt, out: &mut Output) {
+    let mut names: IndexMap<OsString, Vec<&FileGroup>> = IndexMap::new();
+    for grp in groups.iter().filter(|grp| grp.is_sequence()) {
+        names.entry(grp.name_in(opt.pattern_style)).or_default().push(grp);
+    }
+    names.retain(|_, variants| variants.len() > 1);
+    names.sort_keys();
+    let quoting = opt.quoting_style();
+    for (name, mut variants) in names {
+        variants.sort_by(|a, b| a.parent.cmp(&b.parent));
+        let _ = writeln!(out, "{}", opt.style(&lsn::quote_os_str(&name, quoting), Style::Bold));
+        let rows: Vec<_> = variants
+            .iter()
+            .map(|grp| {
+                let dir = grp.parent.as_deref().unwrap_or(Path::new(""));
+                let shown = opt.display_dir(dir);
+                let shown = if shown.as_os_str().is_empty() { PathBuf::from(".") } else { shown };
+                let shown = format!("{}/", lsn::quote_os_str(shown.as_os_str(), quoting));
+                let span = grp.span().map(|(first, last)| format!("{}..{}", first, last)).unwrap_or_default();
+                let count = format!("{} file{}", lsn::format_thousands(grp.count() as u64), if grp.count() == 1 { "" } else { "s" });
+                let size = grp.size().map(|size| opt.format_size(size)).unwrap_or_default();
+                (dir, shown, span, count, size)
+            })
+            .collect();
+        let width = |column: usize| {
+            rows.iter().map(|row| [&row.1, &row.2, &row.3, &row.4][column]).map(|text| lsn::display_width(text)).max().unwrap_or(0)
+        };
+        let (dir_width, span_width, count_width, size_width) = (width(0), width(1), width(2), width(3));
+        for (i, (dir, shown, span, count, size)) in rows.iter().enumerate() {
+            let pad = dir_width - lsn::display_width(shown);
+            let mut line = format!("  {}{:pad$}  {:<span_width$}  {:>count_width$}  {:>size_width$}", opt.paint(Kind::Dir, dir.as_os_str(), shown), "", span, count, size);
+            if let Some(previous) = i.checked_sub(1).map(|i| variants[i]) {
+                let delta = |diff: i128, text: String| match diff {
+                    0 => None,
+                    diff if diff > 0 => Some(opt.style(&format!("+{}", text), Style::Good)),
+                    _ => Some(opt.style(&format!("-{}", text), Style::Bad)),
+                };
+                let (count, previous_count) = (variants[i].count() as i128, previous.count() as i128);
+                let files = delta(count - previous_count, format!("{} files", (count - previous_count).unsigned_abs()));
+                let size = variants[i].size().zip(previous.size()).and_then(|(size, previous_size)| {
+                    let diff = size as i128 - previous_size as i128;
+                    delta(diff, opt.format_size(diff.unsigned_abs() as u64))
+                });
+                for delta in files.into_iter().chain(size) {
+                    line.push_str("  ");
+                    line.push_str(&delta);
+                }
+            }
+            let _ = writeln!(out, "{}", line.trim_end());
+        }
+    }
+}
+
+/// Writes the raw paths of `groups` separated by NUL bytes.
+fn print_zero(groups: &[FileGroup], opt: &Opt, out: &mut Output) {
+    let mut write = |path: &Path| {
+        let _ = out.write_all(path.as_os_str().as_encoded_bytes());
+        let _ = out.write_all(b"\0");
+    };
+    for grp in groups {
+        let parent = grp.parent.clone().unwrap_or_default();
+        if !grp.is_sequence() {
+            let name = match grp.latest() {
+                Some(member) => member.name.clone(),
+                None => {
+                    let mut name = grp.stem.clone();
+                    name.push(&grp.ext);
+                    name
+                }
+            };
+            write(&parent.join(name));
+        } else if opt.expand {
+            let mut members: Vec<_> = grp.members.iter().collect();
+            members.sort_by_key(|m| m.number);
+            for member in members {
+                write(&parent.join(&member.name));
+            }
+        } else {
+            write(&parent.join(grp.canonical_pattern(opt.pattern_style)));
+        }
+    }
+}
+
+fn print_groups(groups: &[FileGroup], opt: &Opt, out: &mut Output) {
+    let start = Instant::now();
+    if opt.zero {
+        print_zero(groups, opt, out);
+    } else if opt.by_dir {
+        print_by_dir(groups, opt, out);
+    } else if opt.ext_summary {
+        print_ext_summary(groups, opt, out);
+    } else if opt.across_dirs_report {
+        print_across_dirs(groups, opt, out);
+    } else if opt.sections {
+        print_sections(groups, opt, out);
+    } else if opt.format == Format::Ndjson {
+        print_ndjson(groups, opt, out);
+    } else if opt.format == Format::Json {
+        print_json(groups, opt, out);
+    } else if opt.format != Format::Text {
+        print_table(groups, opt, out);
+    } else {
+        print_rows(&groups.iter().collect::<Vec<_>>(), opt, out);
+    }
+    Timings::add(&opt.timings.print, start);
+}
+
+/// Version of the JSON output schema, bumped whenever fields are removed or change meaning. Fields
+/// may be added without bumping it.
+const SCHEMA_VERSION: u32 = 1;
+
+/// Listing printed by `--format json`.
+#[derive(Debug, serde::Serialize)]
+struct Listing {
+    schema_version: u32,
+    groups: Vec<GroupRecord>,
+}
+
+/// A group as printed by `--format json` and `ndjson`.
+#[derive(Debug, serde::Serialize)]
+struct GroupRecord {
+    /// Path of the group, with `#` in place of the number for sequences.
+    path: String,
+    /// Directory of the group, or `null` for paths given without one.
+    dir: Option<String>,
+    name: String,
+    /// One of `file`, `dir`, `symlink`, `executable`, `fifo` or `socket`.
+    kind: &'static str,
+    sequence: bool,
+    /// First and last numbers of numbered groups, or `null`.
+    first: Option<isize>,
+    last: Option<isize>,
+    count: usize,
+    missing: usize,
+    /// Total size in bytes, or `null` if unknown.
+    size: Option<u64>,
+    /// Latest modification time in RFC 3339 format, or `null` if unknown.
+    modified: Option<String>,
+}
+
+impl GroupRecord {
+    fn new(grp: &FileGroup, opt: &Opt) -> Self {
+        let name = grp.name_in(opt.pattern_style);
+        let dir = grp.parent.as_deref().map(|dir| opt.display_dir(dir));
+        let path = dir.as_deref().unwrap_or(Path::new("")).join(&name);
+        let kind = match Kind::of_group(grp) {
+            Kind::File => "file",
+            Kind::Dir => "dir",
+            Kind::Symlink => "symlink",
+            Kind::Executable => "executable",
+            Kind::Fifo => "fifo",
+            Kind::Socket => "socket",
+        };
+        GroupRecord {
+            path: lsn::escape_os_str(path.as_os_str()),
+            dir: dir.map(|dir| lsn::escape_os_str(dir.as_os_str())),
+            name: lsn::escape_os_str(&name),
+            kind,
+            sequence: grp.is_sequence(),
+            first: grp.span().map(|(first, _)| first),
+            last: grp.span().map(|(_, last)| last),
+            count: grp.count(),
+            missing: grp.missing_count(),
+            size: grp.size(),
+            modified: grp.modified().map(|time| DateTime::<Local>::from(time).to_rfc3339()),
+        }
+    }
+}
+
+/// Prints all groups as one JSON document.
+fn print_json(groups: &[FileGroup], opt: &Opt, out: &mut Output) {
+    let listing = Listing { schema_version: SCHEMA_VERSION, groups: groups.iter().map(|grp| GroupRecord::new(grp, opt)).collect() };
+    if let Ok(json) = serde_json::to_string_pretty(&listing) {
+        let _ = writeln!(out, "{}", json);
+    }
+}
+
+/// Prints one JSON object per group and line.
+fn print_ndjson(groups: &[FileGroup], opt: &Opt, out: &mut Output) {
+    for grp in groups {
+        if let Ok(line) = serde_json::to_string(&GroupRecord::new(grp, opt)) {
+            let _ = writeln!(out, "{}", line);
+        }
+    }
+}
+
+/// Prints a Markdown or HTML table with one row per group.
+fn print_table(groups: &[FileGroup], opt: &Opt, out: &mut Output) {
+    let header = ["Name", "Range", "Count", "Size", "Modified"];
+    let rows = groups.iter().map(|grp| {
+        let mut path = if opt.show_dirs() {
+            grp.parent.as_deref().map(|dir| opt.display_dir(dir)).unwrap_or_default()
+        } else {
+            PathBuf::new()
+        };
+        path.push(grp.name_in(opt.pattern_style));
+        let range = match &grp.range {
+            Some(_) if grp.is_sequence() => {
+                let (first, last) = grp.span().unwrap();
+                format!("{}..{}", grp.format_number(first, 0), grp.format_number(last, 0))
+            }
+            _ => String::new(),
+        };
+        let size = grp.size().map(|size| opt.format_size(size)).unwrap_or_default();
+        [lsn::escape_os_str(path.as_os_str()), range, grp.count().to_string(), size, opt.format_time(grp.modified())]
+    });
+    match opt.format {
+        Format::Markdown => {
+            let escape = |s: &str| s.replace('\\', "\\\\").replace('|', "\\|");
+            let _ = writeln!(out, "| {} |", header.join(" | "));
+            let _ = writeln!(out, "|{}", " --- |".repeat(header.len()));
+            for row in rows {
+                let row: Vec<_> = row.iter().map(|cell| escape(cell)).collect();
+                let _ = writeln!(out, "| {} |", row.join(" | "));
+            }
+        }
+        Format::Html => {
+            let escape = |s: &str| s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;");
+            let _ = writeln!(out, "<table>");
+            let _ = writeln!(out, "  <tr>{}</tr>", header.iter().map(|h| format!("<th>{}</th>", h)).collect::<String>());
+            for row in rows {
+                let _ = writeln!(out, "  <tr>{}</tr>", row.iter().map(|cell| format!("<td>{}</td>", escape(cell))).collect::<String>());
+            }
+            let _ = writeln!(out, "</table>");
+        }
+        Format::Text | Format::Json | Format::Ndjson => unreachable!(),
+    }
+}
+
+/// Prints directories, sequences and single files under separate headings, keeping the sort order
+/// within each.
+fn print_sections(groups: &[FileGroup], opt: &Opt, out: &mut Output) {
+    let (dirs, rest): (Vec<_>, Vec<_>) = groups.iter().partition(|grp| grp.range.is_none() && grp.is_dir());
+    let (sequences, files): (Vec<_>, Vec<_>) = rest.into_iter().partition(|grp| grp.is_sequence());
+    let sections = [("Directories", dirs), ("Sequences", sequences), ("Files", files)];
+    let mut first = true;
+    for (heading, section) in sections.iter().filter(|(_, section)| !section.is_empty()) {
+        if !first {
+            let _ = writeln!(out);
+        }
+        first = false;
+        let _ = writeln!(out, "{}", opt.style(&format!("{}:", heading), Style::Bold));
+        print_rows(section, opt, out);
+    }
+}
+
+/// Detects the type of `grp` from the first bytes of its first file, along with its extension if
+/// that usually belongs to another type.
+fn detect_kind(grp: &FileGroup) -> Option<(FileKind, Option<String>)> {
+    use std::io::Read;
+    if grp.is_dir() {
+        return None;
+    }
+    let path = grp.first_path();
+    let mut head = Vec::with_capacity(lsn::MAGIC_LEN);
+    std::fs::File::open(&path).ok()?.take(lsn::MAGIC_LEN as u64).read_to_end(&mut head).ok()?;
+    let kind = FileKind::detect(&head)?;
+    let ext = path.extension().unwrap_or_default().to_string_lossy();
+    let mismatch = (FileKind::is_known_extension(&ext) && !kind.has_extension(&ext)).then(|| format!(".{}", ext));
+    Some((kind, mismatch))
+}
+
+/// Prefix of the stem of a sequence up to and including the first `_`, `.` or `-`, by which
+/// `--prefix-tree` collapses sequences.
+fn stem_prefix(grp: &FileGroup) -> Option<&[u8]> {
+    if !grp.is_sequence() || grp.is_dir() {
+        return None;
+    }
+    let stem = grp.common_stem();
+    let end = stem.iter().position(|b| matches!(b, b'_' | b'.' | b'-'))? + 1;
+    Some(&stem[..end])
+}
+
+/// Moves sequences sharing a directory and stem prefix right after the first of them, pairing
+/// each group with the length of its prefix if it is shared with another.
+fn prefix_tree(groups: Vec<&FileGroup>) -> Vec<(&FileGroup, Option<usize>)> {
+    let mut nodes: IndexMap<(Option<&Path>, &[u8]), Vec<&FileGroup>> = IndexMap::new();
+    for &grp in groups.iter() {
+        if let Some(prefix) = stem_prefix(grp) {
+            nodes.entry((grp.parent.as_deref(), prefix)).or_default().push(grp);
+        }
+    }
+    let mut rows = Vec::with_capacity(groups.len());
+    for grp in groups {
+        let Some(prefix) = stem_prefix(grp) else {
+            rows.push((grp, None));
+            continue;
+        };
+        // The first group of a node takes all of its children, leaving the rest empty.
+        let children = std::mem::take(&mut nodes[&(grp.parent.as_deref(), prefix)]);
+        let shared = (children.len() > 1).then_some(prefix.len());
+        rows.extend(children.into_iter().map(|child| (child, shared)));
+    }
+    rows
+}
+
+/// Prints one row per group.
+fn print_rows(groups: &[&FileGroup], opt: &Opt, out: &mut Output) {
+    // Single files past `--max-singles` in each directory are only counted.
+    let mut singles: IndexMap<Option<&Path>, usize> = IndexMap::new();
+    let groups: Vec<&FileGroup> = match opt.max_singles {
+        Some(max) => groups
+            .iter()
+            .copied()
+            .filter(|grp| {
+                if grp.is_sequence() || grp.is_dir() {
+                    return true;
+                }
+                let count = singles.entry(grp.parent.as_deref()).or_default();
+                *count += 1;
+                *count <= max
+            })
+            .collect(),
+        None => groups.to_vec(),
+    };
+    let rows = if opt.prefix_tree {
+        prefix_tree(groups)
+    } else {
+        groups.into_iter().map(|grp| (grp, None)).collect()
+    };
+    let groups: Vec<&FileGroup> = rows.iter().map(|&(grp, _)| grp).collect();
+    let quoting = opt.quoting_style();
+    let length_for_size = groups.iter().map(|x| opt.format_size(x.size().unwrap_or(0)).len()).max().unwrap_or(1);
+    let max_size = groups.iter().filter_map(|x| x.size()).max().unwrap_or(0);
+    let max_inode = groups.iter().filter_map(|x| x.inode()).max().unwrap_or(0);
+    let length_for_inode = if max_inode > 0 {
+        max_inode.ilog10() as usize + 1
+    } else {
+        1
+    };
+    // Columns are widened to fit their headings, and times padded so names stay aligned.
+    let header = opt.header && (opt.long || opt.inode) && !groups.is_empty();
+    let times: Vec<(TimeField, usize)> = opt
+        .show_times
+        .iter()
+        .map(|&field| {
+            let width = groups.iter().map(|x| lsn::display_width(&opt.format_time(field.get(x)))).max().unwrap_or(0);
+            (field, if header { width.max(field.label().len()) } else { width })
+        })
+        .collect();
+    // Owners are shown by name unless `--numeric-ids` is given, or their names are unknown.
+    let owners: Vec<(String, String)> = if opt.long && cfg!(unix) {
+        let mut users = HashMap::new();
+        let mut group_names = HashMap::new();
+        groups
+            .iter()
+            .map(|grp| {
+                let user = grp.uid().map_or("-".to_string(), |uid| {
+                    let name = (!opt.numeric_ids).then(|| users.entry(uid).or_insert_with(|| owner::user_name(uid)).clone()).flatten();
+                    name.unwrap_or_else(|| uid.to_string())
+                });
+                let group = grp.gid().map_or("-".to_string(), |gid| {
+                    let name = (!opt.numeric_ids).then(|| group_names.entry(gid).or_insert_with(|| owner::group_name(gid)).clone()).flatten();
+                    name.unwrap_or_else(|| gid.to_string())
+                });
+                (user, group)
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let length_for_user = owners.iter().map(|(user, _)| lsn::display_width(user)).max().unwrap_or(0);
+    let length_for_group = owners.iter().map(|(_, group)| lsn::display_width(group)).max().unwrap_or(0);
+    let (length_for_size, length_for_inode, length_for_user, length_for_group) = if header {
+        (length_for_size.max(4), length_for_inode.max(5), length_for_user.max(5), length_for_group.max(5))
+    } else {
+        (length_for_size, length_for_inode, length_for_user, length_for_group)
+    };
+    if header {
+        let mut line = String::new();
+        if opt.inode {
+            line.push_str(&format!("{:>length_for_inode$} ", "INODE"));
+        }
+        if !owners.is_empty() {
+            line.push_str(&format!("{:<length_for_user$} {:<length_for_group$} ", "OWNER", "GROUP"));
+        }
+        if opt.long {
+            line.push_str(&format!("{:>length_for_size$} ", "SIZE"));
+            for &(field, width) in times.iter() {
+                line.push_str(&format!("{:<width$} ", field.label()));
+            }
+        }
+        let _ = writeln!(out, "{}NAME", line);
+    }
+    let width = (!opt.no_truncate && std::io::stdout().is_terminal())
+        .then(terminal_size::terminal_size)
+        .flatten()
+        .map(|(w, _)| w.0 as usize);
+
+    /// A row, printed once the widths of the names in its directory are known.
+    struct Line<'a> {
+        dir: Option<&'a Path>,
+        /// Columns, name and indicator.
+        head: String,
+        /// Display width of `head`.
+        width: usize,
+        /// Range and annotations following the name.
+        tail: String,
+        /// Whether `tail` starts with a range, aligned with those of the directory's other rows.
+        aligned: bool,
+    }
+    let mut lines = Vec::new();
+
+    // Directory and prefix of the last `--prefix-tree` node printed.
+    let mut last_node = None;
+    for (i, &(value, shared)) in rows.iter().enumerate() {
+        let mut path = if opt.show_dirs() {
+            value.parent.as_deref().map(|dir| opt.display_dir(dir)).unwrap_or_default()
+        } else {
+            PathBuf::new()
+        };
+        // Children of a node are indented below it without their directory and shared prefix.
+        let mut node = None;
+        if let Some(shared) = shared {
+            let prefix = &value.stem.as_encoded_bytes()[..shared];
+            if last_node != Some((value.parent.as_deref(), prefix)) {
+                last_node = Some((value.parent.as_deref(), prefix));
+                let mut node_path = path.clone();
+                node_path.push(lsn::os_string_from_bytes(prefix));
+                node = Some(format!("{}*", lsn::quote_os_str(node_path.as_os_str(), quoting)));
+            }
+        }
+        let indent = if shared.is_some() { "  " } else { "" };
+        let stem_len = value.stem_label().to_string_lossy().chars().count()
+            - shared.map_or(0, |shared| String::from_utf8_lossy(&value.stem.as_encoded_bytes()[..shared]).chars().count());
+        let mut annotation = String::new();
+        // Range information shown right after the name.
+        let mut detail = String::new();
+        if let Some(ref range) = value.range {
+            let mut file_name = value.stem_label();
+            let udims: Option<Vec<usize>> = value.frames().into_iter().map(|n| usize::try_from(n).ok().filter(|&n| lsn::is_udim(n))).collect();
+            if let Some(udims) = udims.filter(|_| opt.udim) {
+                file_name.push("<UDIM>");
+                file_name.push(value.ext_label());
+                let count = value.count();
+                detail = format!(" ({} tile{}", count, if count == 1 { "" } else { "s" });
+                let missing = lsn::missing_udims(&udims);
+                if !missing.is_empty() {
+                    let missing = missing.iter().map(ToString::to_string).collect::<Vec<_>>();
+                    detail.push_str(&format!(", missing {}", missing.join(", ")));
+                }
+                detail.push(')');
+            } else if !value.is_sequence() {
+                file_name = value.latest().unwrap().name.clone();
+            } else if value.versioned {
+                file_name.push(value.placeholder());
+                file_name.push(value.ext_label());
+                detail = format!(" (v{}..v{})", value.format_number(range.start, 0), value.format_number(range.end - 1, 0));
+                let latest = lsn::quote_os_str(&value.latest().unwrap().name, quoting);
+                annotation = format!(" latest: {}", opt.style(&latest, Style::Bold));
+            } else {
+                file_name.push(value.placeholder_in(opt.pattern_style));
+                file_name.push(value.ext_label());
+                let (first, last) = value.span().unwrap();
+                detail = match opt.fps {
+                    Some(fps) if !value.is_dir() && !value.dated => {
+                        let frames = value.span_len();
+                        format!(" ({}..{}, {} frames @ {}fps = {})", value.format_number(first, 0), value.format_number(last, 0), frames, fps, lsn::format_frame_time(frames, fps))
+                    }
+                    _ => format!(" ({}..{})", value.format_number(first, 0), value.format_number(last, 0)),
+                };
+            }
+            if value.dated && value.is_sequence() && detail.ends_with(')') {
+                let missing = value.missing_count();
+                if missing > 0 {
+                    detail.insert_str(detail.len() - 1, &format!(", {} day{} missing", missing, if missing == 1 { "" } else { "s" }));
+                }
+            }
+            if opt.indicators && value.is_sequence() {
+                let missing = value.missing_count();
+                let (indicator, style) = if missing == 0 {
+                    ("✓".to_string(), Style::Good)
+                } else {
+                    (format!("!{}", missing), Style::Bad)
+                };
+                detail.push_str(&format!(" {}", opt.style(&indicator, style)));
+            }
+            for (ext, missing) in value.missing_per_ext() {
+                annotation.push_str(&format!(" [{}: {} missing]", lsn::escape_os_str(ext), missing));
+            }
+            if opt.stats && value.is_sequence() {
+                if let Some(stats) = value.size_stats() {
+                    annotation.push_str(&format!(" [{}]", stats));
+                }
+            }
+            let duplicates = value.duplicates();
+            if !duplicates.is_empty() {
+                let extra: usize = duplicates.iter().map(|(_, names)| names.len() - 1).sum();
+                annotation.push_str(&format!(" [{} duplicate{}]", extra, if extra == 1 { "" } else { "s" }));
+            }
+            if opt.inode {
+                let linked = value.hard_linked();
+                if linked > 0 {
+                    annotation.push_str(&format!(" [{} hard-linked]", linked));
+                }
+            }
+            match shared {
+                Some(shared) => path = PathBuf::from(lsn::os_string_from_bytes(&file_name.as_encoded_bytes()[shared..])),
+                None => path.push(file_name),
+            }
+        } else {
+            let mut filename = value.stem.clone();
+            filename.push(&value.ext);
+            if opt.mounts && value.is_dir() {
+                let dir = value.parent.clone().unwrap_or_default().join(&filename);
+                if let Some(mount) = mount_point(&dir) {
+                    annotation.push_str(&format!(" [mount: {}]", mount.display()));
+                }
+            }
+            path.push(filename);
+        }
+        if let Some(change) = opt.changes.get(&value.path()) {
+            let (text, style) = match change {
+                None => ("[new]".to_string(), Style::Good),
+                Some(0) => ("[changed]".to_string(), Style::Good),
+                Some(delta) if *delta > 0 => (format!("[+{}]", delta), Style::Good),
+                Some(delta) => (format!("[{}]", delta), Style::Bad),
+            };
+            annotation.push_str(&format!(" {}", opt.style(&text, style)));
+        }
+        if opt.mime {
+            if let Some((kind, mismatch)) = detect_kind(value) {
+                match mismatch {
+                    Some(ext) => annotation.push_str(&format!(" {}", opt.style(&format!("[{}, named {}]", kind.mime, ext), Style::Bad))),
+                    None => annotation.push_str(&format!(" [{}]", kind.mime)),
+                }
+            }
+        }
+        let mut columns = String::new();
+        if opt.inode {
+            let inode = value.inode().map(|ino| ino.to_string()).unwrap_or("-".to_string());
+            columns.push_str(&format!("{:>length_for_inode$} ", inode));
+        }
+        if let Some((user, group)) = owners.get(i) {
+            columns.push_str(&format!("{:<length_for_user$} {:<length_for_group$} ", user, group));
+        }
+        if opt.long {
+            columns.push_str(&opt.paint_size(value.size(), max_size, length_for_size));
+            columns.push(' ');
+            for &(field, width) in times.iter() {
+                columns.push_str(&opt.paint_time(field.get(value), width));
+                columns.push(' ');
+            }
+        }
+        if let Some(node) = node {
+            let node = format!("{:width$}{}", "", opt.style(&node, Style::Bold), width = lsn::display_width(&columns));
+            lines.push(Line { dir: value.parent.as_deref(), width: lsn::display_width(&node), head: node, tail: String::new(), aligned: false });
+        }
+        let mut path_str = lsn::quote_os_str(path.as_os_str(), quoting);
+        let kind = Kind::of_group(value);
+        // A root such as `/` already ends in its indicator.
+        let suffix = if path.as_os_str().as_encoded_bytes().ends_with(b"/") { "" } else { opt.indicator_style().indicator(kind) };
+        if let Some(width) = width {
+            // Everything after the stem, such as the placeholder and extension, stays visible.
+            let name_len = path.file_name().map_or(0, |name| name.to_string_lossy().chars().count());
+            let tail = name_len.saturating_sub(stem_len) + usize::from(quoting == QuotingStyle::C);
+            let rest = lsn::display_width(&columns) + indent.len() + suffix.len() + lsn::display_width(&detail) + lsn::display_width(&annotation);
+            path_str = lsn::truncate_middle(&path_str, tail, width.saturating_sub(rest));
+        }
+        let name = opt.paint(kind, path.as_os_str(), &path_str);
+        let name = if opt.hyperlink {
+            let dir = if value.is_dir() && value.range.is_none() {
+                let mut file_name = value.stem.clone();
+                file_name.push(&value.ext);
+                value.parent.clone().unwrap_or_default().join(file_name)
+            } else {
+                value.parent.clone().unwrap_or_default()
+            };
+            let dir = if dir.as_os_str().is_empty() { PathBuf::from(".") } else { dir };
+            let dir = std::fs::canonicalize(&dir).unwrap_or(dir);
+            lsn::hyperlink(&lsn::file_url(&dir), &name)
+        } else {
+            name
+        };
+        let head = format!("{}{}{}{}", columns, indent, name, suffix);
+        lines.push(Line { dir: value.parent.as_deref(), width: lsn::display_width(&head), head, aligned: !detail.is_empty(), tail: detail + &annotation });
+    }
+
+    // Ranges start in the same column for all sequences in a directory.
+    let mut columns: HashMap<Option<&Path>, usize> = HashMap::new();
+    for line in lines.iter().filter(|line| line.aligned) {
+        let column = columns.entry(line.dir).or_default();
+        *column = (*column).max(line.width);
+    }
+    for line in lines {
+        let column = if line.aligned { columns[&line.dir] } else { line.width };
+        // Padding is dropped rather than wrapping lines that would no longer fit.
+        let fits = width.is_none_or(|width| column + lsn::display_width(&line.tail) <= width);
+        let pad = if fits { column - line.width } else { 0 };
+        let _ = writeln!(out, "{}{:pad$}{}", line.head, "", line.tail);
+    }
+    let max = opt.max_singles.unwrap_or(0);
+    for (dir, count) in singles.into_iter().filter(|&(_, count)| count > max) {
+        let dir = match dir {
+            Some(dir) if opt.show_dirs() => format!(" in {}", lsn::quote_os_str(opt.display_dir(dir).as_os_str(), quoting)),
+            _ => String::new(),
+        };
+        let hidden = count - max;
+        let _ = writeln!(out, "… and {} other file{}{}", lsn::format_thousands(hidden as u64), if hidden == 1 { "" } else { "s" }, dir);
+    }
+}
+
+/// Lists a single sequence, optionally restricted to `frames`, reporting which of those frames are
+/// missing.
+fn list_sequence(spec: &str, frames: Option<&FrameSet>, matcher: &Matcher, opt: &Opt, out: &mut Output) -> Result<(), String> {
+    let grp = match (find_group(spec, matcher, opt), frames) {
+        (Ok(grp), _) => Some(grp),
+        (Err(_), Some(_)) => None,
+        (Err(err), None) => return Err(err),
+    };
+    let Some(frames) = frames else {
+        print_groups(&[grp.unwrap()], opt, out);
+        return Ok(());
+    };
+    let mut present = FrameSet::default();
+    if let Some(mut grp) = grp {
+        grp.retain_members(|m| frames.contains(m.number));
+        present = grp.frames().into_iter().collect();
+        if !grp.members.is_empty() {
+            print_groups(&[grp], opt, out);
+        }
+    }
+    let missing = frames.difference(&present);
+    if missing.is_empty() {
+        let _ = writeln!(out, "{}/{} frames present", frames.len(), frames.len());
+    } else {
+        let _ = writeln!(out, "{}/{} frames present, missing {}", frames.len() - missing.len(), frames.len(), missing);
+    }
+    Ok(())
+}
+
+/// Splits a frame set suffix such as the `@1-100` of `frame#.exr@1-100` from a sequence spec.
+/// Only the file name is searched, and an `@` not followed by a frame set is part of the name.
+fn split_frame_suffix(spec: &str) -> (&str, Option<FrameSet>) {
+    let name_start = spec.rfind('/').map_or(0, |i| i + 1);
+    match spec[name_start..].rsplit_once('@') {
+        Some((name, frames)) => match frames.parse() {
+            Ok(frames) => (&spec[..name_start + name.len()], Some(frames)),
+            Err(_) => (spec, None),
+        },
+        None => (spec, None),
+    }
+}
+
+fn man() -> Result<(), String> {
+    let cmd = Opt::augment_args(clap::Command::new("lsn")).about(ABOUT.trim());
+    clap_mangen::Man::new(cmd)
+        .render(&mut std::io::stdout())
+        .map_err(|e| format!("failed to write man page: {}", e))
+}
+
+/// Scans the listed paths, returning their prepared groups.
+fn scan(entries: &[PathBuf], matcher: &Matcher, opt: &Opt) -> Vec<FileGroup> {
+    let mut groups = Vec::new();
+    scan_each(entries, matcher, opt, |batch| groups.extend(batch));
+    groups
+}
+
+/// Scans the listed paths, passing their prepared groups to `emit`. With `--stream` the groups of
+/// each directory are passed as soon as everything inside it is walked, and otherwise all of them
+/// are passed at once at the end.
+fn scan_each(entries: &[PathBuf], matcher: &Matcher, opt: &Opt, mut emit: impl FnMut(Vec<FileGroup>)) {
+    let mut progress = Progress::new(!opt.no_progress && std::io::stderr().is_terminal());
+    // Directory sizes need a full walk, even below the listed depth.
+    let list_depth = if opt.directory { 0 } else { opt.depth };
+    let mut du = opt.du.then(|| DiskUsage::new(list_depth));
+    let walk_depth = if opt.du { usize::MAX } else { list_depth };
+    // Directories given as the path are descended into rather than listed, unless `-d` is used.
+    let listed = |e: &walkdir::DirEntry| e.depth() <= list_depth && (opt.directory || e.depth() > 0 || !e.file_type().is_dir());
+
+    // Only the contents of listed directories are cached, so deeper walks always read the disk.
+    let use_cache = opt.cache && walk_depth == 1 && !opt.directory && !opt.stream;
+    let visited = RefCell::new(Visited::default());
+    let keeps = |e: &walkdir::DirEntry| !opt.follow_links || visited.borrow_mut().keeps(e);
+    // Groups by the directory they're in while streaming, or all under one key otherwise. The
+    // scanners of all directories share the rules of `.lsnignore` files.
+    let scanner = opt.scanner(matcher);
+    let mut pending: IndexMap<PathBuf, Scanner> = IndexMap::new();
+    let mut truncated = false;
+    'walk: for path in entries {
+        if use_cache {
+            if let Some(cached) = cache::load(path, opt.follow_links) {
+                opt.timings.entries.set(opt.timings.entries.get() + cached.len());
+                group_cached(path, cached, opt, pending.entry(PathBuf::new()).or_insert_with(|| scanner.clone()));
+                continue;
+            }
+        }
+        let mut snapshot = Vec::new();
+        let mut complete = true;
+        // Walking contents first means a directory's entry is yielded only after everything
+        // inside it, so skipped directories are still walked and their entries dropped one by one.
+        let walker = WalkDir::new(path)
+            .max_depth(walk_depth)
+            .follow_links(opt.follow_links)
+            .same_file_system(opt.one_file_system)
+            .contents_first(opt.stream)
+            .into_iter()
+            .filter_entry(|e| opt.stream || keeps(e));
+        for entry in walker {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    complete = false;
+                    walk_error(err);
+                    continue;
+                }
+            };
+            if opt.stream && !keeps(&entry) {
+                continue;
+            }
+            if opt.max_entries.is_some_and(|max| progress.scanned == max) {
+                truncated = true;
+                break 'walk;
+            }
+            progress.tick(entry.path());
+            if let Some(du) = du.as_mut() {
+                du.add(&entry);
+            }
+            if use_cache && entry.depth() == 1 {
+                snapshot.push((entry.file_name().to_owned(), Kind::of(entry.file_type())));
+            }
+            if !listed(&entry) {
+                continue;
+            }
+            if !opt.stream {
+                group_entries(std::iter::once(entry), opt, pending.entry(PathBuf::new()).or_insert_with(|| scanner.clone()));
+                continue;
+            }
+            let dir = entry.file_type().is_dir().then(|| entry.path().to_owned());
+            let parent = entry.path().parent().map(ToOwned::to_owned).unwrap_or_default();
+            group_entries(std::iter::once(entry), opt, pending.entry(parent).or_insert_with(|| scanner.clone()));
+            if let Some(done) = dir.and_then(|dir| pending.shift_remove(&dir)) {
+                progress.clear();
+                emit(prepare_groups(done, du.as_ref(), opt));
+            }
+        }
+        if use_cache && complete {
+            cache::store(path, opt.follow_links, &snapshot);
+        }
+    }
+    progress.clear();
+    if let Some(max) = opt.max_entries.filter(|_| truncated) {
+        warn_max_entries(max);
+    }
+    opt.timings.entries.set(opt.timings.entries.get() + progress.scanned);
+    for (_, done) in pending {
+        emit(prepare_groups(done, du.as_ref(), opt));
+    }
+}
+
+/// Frames of a sequence as of a `--refresh` listing, compared with the next one for `--log`.
+struct SequenceState {
+    /// Size and modification time of each frame.
+    frames: HashMap<isize, (u64, Option<SystemTime>)>,
+    complete: bool,
+}
+
+impl SequenceState {
+    fn new(grp: &FileGroup) -> Self {
+        let frames = grp.members.iter().map(|m| (m.number, m.meta.as_ref().map_or((0, None), |meta| (meta.size, meta.modified)))).collect();
+        SequenceState { frames, complete: grp.missing_count() == 0 }
+    }
+}
+
+/// An event written to the `--log` of `--refresh` as one line of JSON.
+#[derive(Debug, serde::Serialize)]
+struct WatchEvent<'a> {
+    /// Time of the listing that noticed the event, in RFC 3339 format.
+    time: &'a str,
+    /// One of `appeared`, `changed` or `removed` for frames and `completed` for sequences whose
+    /// gaps were filled.
+    event: &'static str,
+    /// Path of the sequence, with `#` in place of the number.
+    sequence: &'a str,
+    /// Number of the frame, or `null` for `completed`.
+    frame: Option<isize>,
+}
+
+/// Events between two listings of the sequences in `current`, sorted by sequence and frame.
+fn watch_events<'a>(previous: &'a HashMap<OsString, SequenceState>, current: &'a HashMap<OsString, SequenceState>) -> Vec<(&'a OsString, &'static str, Option<isize>)> {
+    let empty = SequenceState { frames: HashMap::new(), complete: false };
+    let mut events = Vec::new();
+    let paths: BTreeSet<&OsString> = previous.keys().chain(current.keys()).collect();
+    for path in paths {
+        let (before, after) = (previous.get(path).unwrap_or(&empty), current.get(path).unwrap_or(&empty));
+        let numbers: BTreeSet<isize> = before.frames.keys().chain(after.frames.keys()).copied().collect();
+        for number in numbers {
+            let event = match (before.frames.get(&number), after.frames.get(&number)) {
+                (None, Some(_)) => "appeared",
+                (Some(_), None) => "removed",
+                (Some(old), Some(new)) if old != new => "changed",
+                _ => continue,
+            };
+            events.push((path, event, Some(number)));
+        }
+        if previous.contains_key(path) && after.complete && !before.complete {
+            events.push((path, "completed", None));
+        }
+    }
+    events
+}
+
+/// Lists the scanned paths again every `interval` until interrupted, clearing the terminal
+/// in between and marking groups that changed since the previous listing.
+fn refresh(entries: &[PathBuf], interval: Duration, matcher: &Matcher, mut opt: Opt) -> ! {
+    let clear = std::io::stdout().is_terminal();
+    let mut previous: Option<HashMap<_, _>> = None;
+    let mut log = opt.log.as_ref().map(|path| {
+        let file = std::fs::OpenOptions::new().create(true).append(true).open(path);
+        (path, file.unwrap_or_else(|err| fatal(format_args!("cannot open log '{}': {}", path.display(), err))))
+    });
+    let mut sequences: Option<HashMap<_, _>> = None;
+    loop {
+        let groups = scan(entries, matcher, &opt);
+        if let Some((path, file)) = log.as_mut() {
+            let current: HashMap<_, _> = groups.iter().filter(|grp| grp.is_sequence()).map(|grp| (grp.path(), SequenceState::new(grp))).collect();
+            // The first listing is the baseline the events are relative to.
+            if let Some(previous) = &sequences {
+                let time = Local::now().to_rfc3339();
+                for (sequence, event, frame) in watch_events(previous, &current) {
+                    let sequence = lsn::escape_os_str(sequence);
+                    let record = WatchEvent { time: &time, event, sequence: &sequence, frame };
+                    let written = serde_json::to_string(&record).map_err(std::io::Error::from).and_then(|json| writeln!(file, "{}", json));
+                    if let Err(err) = written {
+                        warn(format_args!("cannot write to log '{}': {}", path.display(), err));
+                        break;
+                    }
+                }
+            }
+            sequences = Some(current);
+        }
+        let current: HashMap<_, _> = groups.iter().map(|grp| (grp.path(), (grp.count(), grp.span()))).collect();
+        opt.changes = match &previous {
+            Some(previous) => current
+                .iter()
+                .filter_map(|(path, &(count, span))| match previous.get(path) {
+                    None => Some((path.clone(), None)),
+                    Some(&(old_count, old_span)) if (old_count, old_span) != (count, span) => Some((path.clone(), Some(count as isize - old_count as isize))),
+                    Some(_) => None,
+                })
+                .collect(),
+            None => HashMap::new(),
+        };
+        previous = Some(current);
+
+        let mut out = Output::new(Paging::Never);
+        if clear {
+            let _ = write!(out, "\x1b[H\x1b[2J");
+        }
+        let now = DateTime::<Local>::from(SystemTime::now()).format("%H:%M:%S");
+        let _ = writeln!(out, "{}", opt.style(&format!("Every {:?}: lsn {}  {}", interval, opt.path, now), Style::Bold));
+        print_groups(&groups, &opt, &mut out);
+        let _ = out.flush();
+        out.finish();
+        std::thread::sleep(interval);
+    }
+}
+
+/// Replaces arguments like `@wrangle` naming a preset from the config with the preset's flags.
+/// Arguments after `--`, values of options such as the `@out.tar` of `-o @out.tar`, and paths
+/// starting with `@` that exist are left alone.
+fn expand_presets(args: Vec<OsString>, config: &Result<Config, String>) -> Result<Vec<OsString>, String> {
+    let mut expanded = Vec::with_capacity(args.len());
+    let mut position = ArgPosition { cmd: Opt::augment_args(clap::Command::new("lsn")), value: false };
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" && !position.value {
+            expanded.push(arg);
+            expanded.extend(args.by_ref());
+            break;
+        }
+        expand_preset(arg, config, &mut position, &mut Vec::new(), &mut expanded)?;
+    }
+    Ok(expanded)
+}
+
+/// Tracks the (sub)command an argument belongs to and whether it is the value of the option
+/// before it, so that only arguments where a flag could go are taken as presets.
+struct ArgPosition {
+    cmd: clap::Command,
+    /// Whether the next argument is the value of an option.
+    value: bool,
+}
+
+impl ArgPosition {
+    /// Moves past `arg`, returning whether it is the value of the option before it.
+    fn next(&mut self, arg: &OsStr) -> bool {
+        if std::mem::take(&mut self.value) {
+            return true;
+        }
+        let Some(arg) = arg.to_str() else {
+            return false;
+        };
+        let takes_value = |matches: &dyn Fn(&clap::Arg) -> bool| self.cmd.get_arguments().any(|a| matches(a) && a.get_action().takes_values());
+        if let Some(long) = arg.strip_prefix("--") {
+            // `--name=value` holds its own value.
+            self.value = !long.contains('=') && takes_value(&|a| a.get_long_and_visible_aliases().is_some_and(|names| names.contains(&long)));
+        } else if let Some(shorts) = arg.strip_prefix('-').filter(|shorts| !shorts.is_empty()) {
+            // In a cluster like `-lo`, an option taking a value takes the rest of the cluster, or
+            // the next argument if it ends the cluster.
+            let value_at = shorts.char_indices().find(|&(_, c)| takes_value(&|a| a.get_short_and_visible_aliases().is_some_and(|names| names.contains(&c))));
+            self.value = value_at.is_some_and(|(i, c)| i + c.len_utf8() == shorts.len());
+        } else if let Some(sub) = self.cmd.find_subcommand(arg) {
+            self.cmd = sub.clone();
+        }
+        false
+    }
+}
+
+/// Appends the flags of `arg` if it names a preset, or else `arg` itself. `outer` holds the
+/// presets being expanded that led to `arg`, to reject presets that refer back to themselves.
+fn expand_preset<'a>(
+    arg: OsString,
+    config: &'a Result<Config, String>,
+    position: &mut ArgPosition,
+    outer: &mut Vec<&'a str>,
+    expanded: &mut Vec<OsString>,
+) -> Result<(), String> {
+    // An argument naming an existing file is a path, even if it starts with `@`.
+    let preset = arg.to_str().and_then(|arg| arg.strip_prefix('@')).filter(|_| Path::new(&arg).symlink_metadata().is_err());
+    let value = position.next(&arg);
+    let Some(name) = preset.filter(|_| !value) else {
+        expanded.push(arg);
+        return Ok(());
+    };
+    let (name, flags) = match config {
+        Ok(config) => config.presets.get_key_value(name).ok_or_else(|| format!("unknown preset '@{}'", name))?,
+        Err(err) => return Err(format!("cannot use preset '@{}': {}", name, err)),
+    };
+    if let Some(position) = outer.iter().position(|outer| outer == name) {
+        let through: Vec<_> = outer[position + 1..].iter().map(|name| format!("'@{}'", name)).collect();
+        return Err(if through.is_empty() {
+            format!("preset '@{}' refers to itself", name)
+        } else {
+            format!("preset '@{}' refers back to itself through {}", name, through.join(", "))
+        });
+    }
+    outer.push(name);
+    for flag in lsn::split_args(flags) {
+        expand_preset(flag.into(), config, position, outer, expanded)?;
+    }
+    outer.pop();
+    Ok(())
+}
+
+fn main() {
+    // Default flags from the environment come before those on the command line so the latter
+    // take precedence.
+    let mut args: Vec<OsString> = std::env::args_os().collect();
+    if let Ok(opts) = std::env::var("LSN_OPTS") {
+        let position = 1.min(args.len());
+        args.splice(position..position, lsn::split_args(&opts).into_iter().map(OsString::from));
+    }
+    let config = Config::load();
+    args = expand_presets(args, &config).unwrap_or_else(|err| fatal(err));
+
+    let cli = clap::Command::new("lsn");
+    let cli = Opt::augment_args(cli);
+    let matches = cli.get_matches_from(args);
+    let mut opt = Opt::from_arg_matches(&matches).unwrap();
+    if opt.json {
+        opt.format = Format::Json;
+    }
+    if opt.stream && matches!(opt.format, Format::Markdown | Format::Html | Format::Json) {
+        fatal("--stream can't print whole documents, use --format ndjson instead");
+    }
+    opt.colors = Colors::from_env();
+    let matcher = opt.pattern.clone().unwrap_or_default().with_radix(opt.radix).with_signed(opt.signed);
+    QUIET.store(opt.quiet, AtomicOrdering::Relaxed);
+    match config {
+        Ok(config) => {
+            for (key, style) in &config.colors {
+                if let Err(err) = opt.colors.add_rule(key, style) {
+                    warn(format_args!("{}", err));
+                }
+            }
+            if let Some(Err(err)) = config.ages.as_ref().map(|ages| opt.colors.set_ages(ages)) {
+                warn(format_args!("{}", err));
+            }
+            for stem in &config.hide_stems {
+                match glob::Pattern::new(stem) {
+                    Ok(glob) => opt.hide_stem.push(glob),
+                    Err(err) => warn(format_args!("invalid stem '{}' in hide_stems: {}", stem, err)),
+                }
+            }
+        }
+        Err(err) => warn(format_args!("{}", err)),
+    }
+
+    if let Some(command) = &opt.command {
+        let result = match command {
+            Command::Link(link_opt) => link(link_opt, &matcher, &opt),
+            Command::Tar(tar_opt) => tar(tar_opt, &matcher, &opt),
+            Command::Repad(repad_opt) => repad(repad_opt, &matcher, &opt),
+            Command::Touch(touch_opt) => touch(touch_opt, &matcher, &opt),
+            Command::Expand(expand_opt) => expand(expand_opt),
+            Command::Frames(frames_opt) => frames(frames_opt),
+            Command::Exec(exec_opt) => exec(exec_opt, &matcher, &opt),
+            Command::Open(open_opt) => open(open_opt, &matcher, &opt),
+            Command::Stat(stat_opt) => stat(stat_opt, &matcher, &opt),
+            Command::Check(check_opt) => check(check_opt, &matcher, &opt),
+            Command::Exists(exists_opt) => exists(exists_opt, &matcher, &opt),
+            Command::Man => man(),
+        };
+        if let Err(err) = result {
+            fatal(err);
+        }
+        exit_on_partial_failure();
+        return;
+    }
+
+    // Brace expressions such as `frame{1..100}.exr` check which of the frames they expand to exist.
+    let brace_spec = lsn::parse_brace_spec(&opt.path).filter(|_| !opt.literal && Path::new(&opt.path).symlink_metadata().is_err());
+    if let Some((spec, frames)) = brace_spec {
+        let mut out = Output::new(opt.paging);
+        let result = list_sequence(&spec, Some(&frames), &matcher, &opt, &mut out);
+        out.finish();
+        if let Err(err) = result {
+            fatal(err);
+        }
+        exit_on_partial_failure();
+        return;
+    }
+
+    if Path::new(&opt.path).file_name().is_some_and(|name| name.to_string_lossy().contains('#')) {
+        let (spec, frames) = split_frame_suffix(&opt.path);
+        let frames = frames.as_ref().or(opt.frames.as_ref());
+        let mut out = Output::new(opt.paging);
+        let result = list_sequence(spec, frames, &matcher, &opt, &mut out);
+        out.finish();
+        if let Err(err) = result {
+            fatal(err);
+        }
+        exit_on_partial_failure();
+        return;
+    }
+
+    if opt.archive {
+        let path = Path::new(&opt.path);
+        let entries = archive::list(path).unwrap_or_else(|err| fatal(err));
+        let depth = if opt.directory { 0 } else { opt.depth };
+        let entries = entries.into_iter().map(|entry| {
+            let kind = if entry.is_dir { Kind::Dir } else { Kind::File };
+            (path.join(&entry.path), entry.path.components().count(), Meta { size: entry.size, modified: entry.modified, ..kind.meta() })
+        });
+        // The archive itself stands in for its root directory.
+        let root = (path.to_owned(), 0, Kind::Dir.meta());
+        print_listed(std::iter::once(root).chain(entries).filter(|(_, d, _)| *d <= depth), &matcher, &opt);
+        return;
+    }
+
+    if opt.path.starts_with("s3://") {
+        #[cfg(not(feature = "remote"))]
+        fatal("listing s3:// URLs needs lsn built with the `remote` feature");
+        #[cfg(feature = "remote")]
+        {
+            let depth = if opt.directory { 0 } else { opt.depth };
+            let url = remote::Url::parse(&opt.path).unwrap_or_else(|err| fatal(err));
+            let objects = remote::list(&url, depth).unwrap_or_else(|err| fatal(err));
+            let entries = objects.into_iter().map(|obj| {
+                let kind = if obj.is_dir { Kind::Dir } else { Kind::File };
+                (PathBuf::from(url.of(&obj.key)), obj.depth, Meta { size: obj.size, modified: obj.modified, ..kind.meta() })
+            });
+            print_listed(entries, &matcher, &opt);
+            return;
+        }
+    }
+
+    let glob_options = glob::MatchOptions {
+        case_sensitive: true,
+        require_literal_separator: true,
+        require_literal_leading_dot: false,
+    };
+
+    // Paths that exist or have no glob characters are used as is, so names containing `[` or `*`
+    // can be listed.
+    let literal = opt.literal || !opt.path.contains(['*', '?', '[']) || Path::new(&opt.path).symlink_metadata().is_ok();
+    let entries: Vec<_> = if literal {
+        Some(PathBuf::from(&opt.path)).filter(|path| path.symlink_metadata().is_ok()).into_iter().collect()
+    } else {
+        match glob::glob_with(&opt.path, glob_options) {
+            Ok(paths) => paths.filter_map(|e| e.map_err(glob_error).ok()).collect(),
+            Err(err) => fatal(format_args!("invalid pattern '{}': {}", opt.path, err)),
+        }
+    };
+    if entries.is_empty() && !PARTIAL_FAILURE.load(AtomicOrdering::Relaxed) {
+        fatal(format_args!("cannot access '{}': No such file or directory", opt.path));
+    }
+
+    if opt.stream {
+        let mut out = Output::new(opt.paging);
+        scan_each(&entries, &matcher, &opt, |groups| {
+            print_groups(&groups, &opt, &mut out);
+            let _ = out.flush();
+        });
+        let elapsed = opt.timings.start.elapsed();
+        out.finish();
+        if opt.timing {
+            opt.timings.report(elapsed);
+        }
+        exit_on_partial_failure();
+        return;
+    }
+
+    if let Some(interval) = opt.refresh {
+        if interval.is_zero() {
+            fatal("--refresh needs an interval longer than zero");
+        }
+        refresh(&entries, interval, &matcher, opt);
+    }
+    let groups = scan(&entries, &matcher, &opt);
+    let mut out = Output::new(opt.paging);
+    print_groups(&groups, &opt, &mut out);
+    let elapsed = opt.timings.start.elapsed();
+    out.finish();
+    if opt.timing {
+        opt.timings.report(elapsed);
+    }
+    exit_on_partial_failure();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opt(args: &[&str]) -> Opt {
+        Opt::parse_from(std::iter::once("lsn").chain(args.iter().copied()))
+    }
+
+    /// A sequence in `render` with members added in the given order, each of the given size.
+    fn sequence(members: &[(&str, isize, u64)]) -> FileGroup {
+        let mut grp = FileGroup::numbered(Some(PathBuf::from("render")), "frame".into(), ".exr".into(), false);
+        for &(name, number, size) in members {
+            grp.add(Member { number, name: name.into(), meta: Some(Meta { size, ..Kind::File.meta() }) });
+        }
+        grp
+    }
+
+    #[test]
+    fn check_report_order() {
+        let grp = sequence(&[
+            ("frame0001.exr", 1, 0),
+            ("frame3.exr", 3, 0),
+            ("frame5.exr", 5, 0),
+            ("frame2.exr", 2, 0),
+            ("frame1.exr", 1, 0),
+            ("frame4.exr", 4, 10),
+        ]);
+        let report = SequenceReport::new(&grp, &opt(&[]));
+        assert_eq!(vec!["frame0001.exr", "frame1.exr", "frame2.exr", "frame3.exr", "frame5.exr"], report.empty);
+        assert_eq!(BTreeSet::from([1, 2, 3, 5]), report.empty_frames);
+        assert_eq!(1, report.duplicates.len());
+        assert_eq!(vec!["frame0001.exr", "frame1.exr"], report.duplicates[0].names);
+        assert_eq!("render/frame#.exr", report.pattern);
+        assert!(!report.is_ok());
+
+        let report = CheckReport { schema_version: SCHEMA_VERSION, problems: 1, sequences: vec![report] };
+        let mut text = Vec::new();
+        write_check_report(&report, &[grp], &mut text).unwrap();
+        let expected = "\
+render/frame#.exr (1..5): problems
+  mixed padding: unpadded, 4 digits
+  5 empty: frame0001.exr, frame1.exr, frame2.exr, frame3.exr, frame5.exr
+  duplicate 1: frame0001.exr, frame1.exr
+1 sequence checked, 1 with problems
+";
+        assert_eq!(expected, String::from_utf8(text).unwrap());
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(serde_json::json!(["frame0001.exr", "frame1.exr", "frame2.exr", "frame3.exr", "frame5.exr"]), json["sequences"][0]["empty"]);
+    }
+
+    #[test]
+    fn check_suggest_runs() {
+        // A huge gap is kept as one range rather than expanded frame by frame.
+        let grp = sequence(&[("frame1.exr", 1, 10), ("frame2000000000.exr", 2_000_000_000, 0)]);
+        let report = SequenceReport::new(&grp, &opt(&[]));
+        let runs = lsn::merge_ranges(report.gaps.iter().copied().chain(report.empty_frames.iter().map(|&n| (n, n))));
+        assert_eq!(vec![(2, 2_000_000_000)], runs);
+        assert_eq!("2-2000000000", grp.format_gaps(&runs));
+    }
+
+    #[test]
+    fn frame_suffixes() {
+        let split = |spec| {
+            let (spec, frames) = split_frame_suffix(spec);
+            (spec, frames.map(|frames| frames.to_string()))
+        };
+        assert_eq!(("frame#.exr", Some("1-3".to_string())), split("frame#.exr@1-3"));
+        assert_eq!(("shots@v2/frame#.exr", None), split("shots@v2/frame#.exr"));
+        assert_eq!(("shots@v2/frame#.exr", Some("5".to_string())), split("shots@v2/frame#.exr@5"));
+        assert_eq!(("render@2x.#.png", None), split("render@2x.#.png"));
+        assert_eq!(("render@2x.#.png", Some("1-2".to_string())), split("render@2x.#.png@1-2"));
+    }
+
+    #[test]
+    fn link_renumber() {
+        let dir = std::env::temp_dir().join(format!("lsn-link-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["f1.exr", "f4.exr", "f9.exr"] {
+            std::fs::write(dir.join(name), "").unwrap();
+        }
+        let run = |spec: &str, dest: &str| {
+            let link_opt = LinkOpt::from_arg_matches(&LinkOpt::augment_args(clap::Command::new("link")).get_matches_from(["link", spec, dest, "--renumber"])).unwrap();
+            link(&link_opt, &Matcher::default(), &opt(&[]))
+        };
+        let dest = dir.join("dense");
+        run(&dir.join("f#.exr").to_string_lossy(), &dest.to_string_lossy()).unwrap();
+        let mut linked: Vec<_> = std::fs::read_dir(&dest).unwrap().map(|e| e.unwrap().file_name().into_string().unwrap()).collect();
+        linked.sort();
+        assert_eq!(vec!["f1.exr", "f2.exr", "f3.exr"], linked);
+
+        std::fs::write(dir.join("f01.exr"), "").unwrap();
+        let err = run(&dir.join("f#.exr").to_string_lossy(), &dir.join("dup").to_string_lossy()).unwrap_err();
+        assert!(err.contains("frame 1 is shared by 'f01.exr' and 'f1.exr'"), "{}", err);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn scan_stream_matches() {
+        let dir = std::env::temp_dir().join(format!("lsn-scan-{}", std::process::id()));
+        for name in ["a/f.1.exr", "a/f.2.exr", "a/b/g.1.dpx", "a/b/g.3.dpx", "top.1.png", "top.2.png"] {
+            let path = dir.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        let paths = |groups: Vec<FileGroup>| groups.iter().map(|grp| grp.path()).collect::<BTreeSet<_>>();
+        let entries = [dir.clone()];
+        let matcher = Matcher::default();
+        let whole = paths(scan(&entries, &matcher, &opt(&["--depth", "3", "--no-progress"])));
+        let mut batches = Vec::new();
+        scan_each(&entries, &matcher, &opt(&["--depth", "3", "--no-progress", "--stream"]), |groups| batches.push(paths(groups)));
+        // Directories are passed after their contents.
+        assert_eq!(3, batches.len());
+        assert!(batches[0].contains(&dir.join("a/b/g.#.dpx").into_os_string()));
+        assert_eq!(whole, batches.into_iter().flatten().collect());
+        assert_eq!(5, whole.len());
+
+        let limited = scan(&entries, &matcher, &opt(&["--depth", "3", "--no-progress", "--max-entries", "2"]));
+        assert!(limited.len() <= 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn state(frames: &[(isize, u64, u64)], complete: bool) -> SequenceState {
+        let frames = frames.iter().map(|&(number, size, secs)| (number, (size, Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))))).collect();
+        SequenceState { frames, complete }
+    }
+
+    #[test]
+    fn watch_events_order() {
+        let previous = HashMap::from([
+            (OsString::from("shots/b/frame#.exr"), state(&[(1, 10, 100), (2, 10, 100), (4, 10, 100)], false)),
+            (OsString::from("shots/a/frame#.exr"), state(&[(1, 10, 100), (2, 10, 100), (3, 10, 100)], true)),
+            (OsString::from("shots/c/frame#.exr"), state(&[(1, 10, 100)], true)),
+        ]);
+        let current = HashMap::from([
+            (OsString::from("shots/b/frame#.exr"), state(&[(1, 10, 100), (2, 20, 100), (3, 10, 200), (4, 10, 100)], true)),
+            (OsString::from("shots/a/frame#.exr"), state(&[(1, 10, 100), (2, 10, 150), (3, 10, 100)], true)),
+            (OsString::from("shots/d/frame#.exr"), state(&[(1, 10, 100), (2, 10, 100)], true)),
+        ]);
+        let events: Vec<_> = watch_events(&previous, &current).into_iter().map(|(path, event, frame)| (path.to_str().unwrap(), event, frame)).collect();
+        assert_eq!(
+            vec![
+                ("shots/a/frame#.exr", "changed", Some(2)),
+                ("shots/b/frame#.exr", "changed", Some(2)),
+                ("shots/b/frame#.exr", "appeared", Some(3)),
+                ("shots/b/frame#.exr", "completed", None),
+                ("shots/c/frame#.exr", "removed", Some(1)),
+                // A sequence that is complete when first seen isn't reported as completed.
+                ("shots/d/frame#.exr", "appeared", Some(1)),
+                ("shots/d/frame#.exr", "appeared", Some(2)),
+            ],
+            events
+        );
+        assert!(watch_events(&current, &current).is_empty());
+    }
+
+    fn presets(presets: &[(&str, &str)]) -> Result<Config, String> {
+        let presets = presets.iter().map(|&(name, flags)| (name.to_string(), flags.to_string())).collect();
+        Ok(Config { presets, ..Config::default() })
+    }
+
+    fn expand(args: &[&str], config: &Result<Config, String>) -> Result<Vec<OsString>, String> {
+        expand_presets(args.iter().map(OsString::from).collect(), config)
+    }
+
+    #[test]
+    fn expand_presets_flags() {
+        let config = presets(&[("wrangle", "-l --sort mtime:desc"), ("recent", "@wrangle -r")]);
+        assert_eq!(Ok(vec!["lsn".into(), "-l".into(), "--sort".into(), "mtime:desc".into(), "shots".into()]), expand(&["lsn", "@wrangle", "shots"], &config));
+        assert_eq!(Ok(vec!["lsn".into(), "-l".into(), "--sort".into(), "mtime:desc".into(), "-r".into()]), expand(&["lsn", "@recent"], &config));
+        // Presets may be used more than once, as long as none refers to itself.
+        assert_eq!(7, expand(&["lsn", "@wrangle", "@wrangle"], &config).unwrap().len());
+        assert_eq!(Ok(vec!["lsn".into(), "--".into(), "@wrangle".into()]), expand(&["lsn", "--", "@wrangle"], &config));
+    }
+
+    #[test]
+    fn expand_presets_values() {
+        let config = presets(&[("wrangle", "-l --sort"), ("out", "-l")]);
+        let unchanged = |args: &[&str]| assert_eq!(Ok(args.iter().map(OsString::from).collect::<Vec<_>>()), expand(args, &config));
+        // Values of options are never presets, even when they name one.
+        unchanged(&["lsn", "--name", "@*", "shots"]);
+        unchanged(&["lsn", "--regex", "@(?P<stem>a)(?P<num>1)(?P<ext>)", "-l"]);
+        unchanged(&["lsn", "tar", "f#.exr", "-o", "@out"]);
+        unchanged(&["lsn", "tar", "f#.exr", "--output", "@out"]);
+        unchanged(&["lsn", "--name=@x", "repad", "f#.exr", "-nw", "@out"]);
+        assert_eq!(Ok(vec!["lsn".into(), "repad".into(), "-w4".into(), "-l".into()]), expand(&["lsn", "repad", "-w4", "@out"], &config));
+        // A preset ending with an option leaves the next argument as its value.
+        assert_eq!(
+            Ok(vec!["lsn".into(), "-l".into(), "--sort".into(), "@out".into(), "-l".into()]),
+            expand(&["lsn", "@wrangle", "@out", "@out"], &config)
+        );
+        assert_eq!(Ok(vec!["lsn".into(), "--name".into(), "--".into(), "-l".into()]), expand(&["lsn", "--name", "--", "@out"], &config));
+    }
+
+    #[test]
+    fn expand_presets_errors() {
+        let config = presets(&[("a", "-l @b"), ("b", "@c"), ("c", "-r @a"), ("me", "-l @me")]);
+        assert_eq!(Err("unknown preset '@missing'".to_string()), expand(&["lsn", "@missing"], &config));
+        assert_eq!(Err("preset '@me' refers to itself".to_string()), expand(&["lsn", "@me"], &config));
+        assert_eq!(Err("preset '@a' refers back to itself through '@b', '@c'".to_string()), expand(&["lsn", "@a"], &config));
+        assert_eq!(Err("preset '@b' refers back to itself through '@c', '@a'".to_string()), expand(&["lsn", "@b"], &config));
+        let broken = Err("invalid config".to_string());
+        assert_eq!(Err("cannot use preset '@a': invalid config".to_string()), expand(&["lsn", "@a"], &broken));
+        assert_eq!(Ok(vec!["lsn".into(), "-l".into()]), expand(&["lsn", "-l"], &broken));
+    }
+
+    #[test]
+    fn expand_presets_paths() {
+        // Tests run in the package directory, where a file named like a preset is created.
+        let name = format!("@lsn-presets-{}", std::process::id());
+        std::fs::write(&name, "").unwrap();
+        let config = presets(&[(&name[1..], "-l")]);
+        let expanded = expand(&["lsn", &name], &config);
+        std::fs::remove_file(&name).unwrap();
+        assert_eq!(Ok(vec!["lsn".into(), name.clone().into()]), expanded);
+        assert_eq!(Ok(vec!["lsn".into(), "-l".into()]), expand(&["lsn", &name], &config));
+    }
+}
@@ -0,0 +1,89 @@
+/// Parses a user given by name or numeric ID into its ID.
+pub fn parse_user(s: &str) -> Result<u32, String> {
+    s.parse().ok().or_else(|| imp::user_id(s)).ok_or_else(|| format!("unknown user '{}'", s))
+}
+
+/// Parses a group given by name or numeric ID into its ID.
+pub fn parse_group(s: &str) -> Result<u32, String> {
+    s.parse().ok().or_else(|| imp::group_id(s)).ok_or_else(|| format!("unknown group '{}'", s))
+}
+
+pub use imp::{group_name, user_name};
+
+#[cfg(unix)]
+mod imp {
+    use std::ffi::{CStr, CString};
+
+    /// Size of the buffer for strings of a database entry, grown until the entry fits.
+    const INITIAL_BUFFER: usize = 1024;
+
+    /// Calls a reentrant `getpw*_r` or `getgr*_r` function, retrying with larger buffers while it
+    /// reports `ERANGE`, and returns what `f` extracts from the entry found.
+    fn lookup<E, T>(call: impl Fn(*mut E, &mut [u8], *mut *mut E) -> libc::c_int, f: impl FnOnce(&E) -> T) -> Option<T> {
+        let mut buf = vec![0u8; INITIAL_BUFFER];
+        loop {
+            let mut entry = std::mem::MaybeUninit::<E>::uninit();
+            let mut result = std::ptr::null_mut();
+            match call(entry.as_mut_ptr(), &mut buf, &mut result) {
+                libc::ERANGE if buf.len() < 1 << 20 => buf.resize(buf.len() * 2, 0),
+                // SAFETY: a non-null result points at the initialized entry.
+                0 if !result.is_null() => return Some(f(unsafe { &*result })),
+                _ => return None,
+            }
+        }
+    }
+
+    /// Name of the user with the given ID.
+    pub fn user_name(uid: u32) -> Option<String> {
+        lookup(
+            // SAFETY: the buffer outlives the call and its length is passed along.
+            |pwd, buf, result| unsafe { libc::getpwuid_r(uid, pwd, buf.as_mut_ptr().cast(), buf.len(), result) },
+            // SAFETY: `pw_name` of an entry is a valid C string.
+            |pwd: &libc::passwd| unsafe { CStr::from_ptr(pwd.pw_name) }.to_string_lossy().into_owned(),
+        )
+    }
+
+    /// Name of the group with the given ID.
+    pub fn group_name(gid: u32) -> Option<String> {
+        lookup(
+            // SAFETY: the buffer outlives the call and its length is passed along.
+            |grp, buf, result| unsafe { libc::getgrgid_r(gid, grp, buf.as_mut_ptr().cast(), buf.len(), result) },
+            // SAFETY: `gr_name` of an entry is a valid C string.
+            |grp: &libc::group| unsafe { CStr::from_ptr(grp.gr_name) }.to_string_lossy().into_owned(),
+        )
+    }
+
+    pub fn user_id(name: &str) -> Option<u32> {
+        let name = CString::new(name).ok()?;
+        lookup(
+            // SAFETY: the name and buffer outlive the call and the buffer's length is passed along.
+            |pwd, buf, result| unsafe { libc::getpwnam_r(name.as_ptr(), pwd, buf.as_mut_ptr().cast(), buf.len(), result) },
+            |pwd: &libc::passwd| pwd.pw_uid,
+        )
+    }
+
+    pub fn group_id(name: &str) -> Option<u32> {
+        let name = CString::new(name).ok()?;
+        lookup(
+            // SAFETY: the name and buffer outlive the call and the buffer's length is passed along.
+            |grp, buf, result| unsafe { libc::getgrnam_r(name.as_ptr(), grp, buf.as_mut_ptr().cast(), buf.len(), result) },
+            |grp: &libc::group| grp.gr_gid,
+        )
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    pub fn user_name(_uid: u32) -> Option<String> {
+        None
+    }
+    pub fn group_name(_gid: u32) -> Option<String> {
+        None
+    }
+    pub fn user_id(_name: &str) -> Option<u32> {
+        None
+    }
+    pub fn group_id(_name: &str) -> Option<u32> {
+        None
+    }
+}
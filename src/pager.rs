@@ -0,0 +1,124 @@
+use std::io::{IsTerminal, Write};
+use std::process::{Child, Command, Stdio};
+
+/// When to send the listing through a pager.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Paging {
+    /// Page only when output to a terminal doesn't fit on the screen.
+    Auto,
+    /// Always page output.
+    Always,
+    /// Never page output.
+    #[default]
+    Never,
+}
+
+enum Sink {
+    Stdout(std::io::Stdout),
+    /// Output held back until it is known whether it fits on the screen.
+    Buffer(Vec<u8>),
+    Pager(Child),
+}
+
+/// Destination for the listing, which may be stdout or a pager.
+///
+/// Write errors such as a closed pipe are not reported, they simply end output.
+pub struct Output {
+    sink: Sink,
+    /// Number of lines after which buffered output is sent to a pager.
+    max_lines: usize,
+    lines: usize,
+    closed: bool,
+}
+
+impl Output {
+    pub fn new(paging: Paging) -> Self {
+        let sink = match paging {
+            Paging::Always => Self::spawn_pager().map_or(Sink::Stdout(std::io::stdout()), Sink::Pager),
+            Paging::Auto if std::io::stdout().is_terminal() => Sink::Buffer(Vec::new()),
+            _ => Sink::Stdout(std::io::stdout()),
+        };
+        let max_lines = terminal_size::terminal_size().map(|(_, h)| h.0 as usize).unwrap_or(24).saturating_sub(1);
+        Output { sink, max_lines, lines: 0, closed: false }
+    }
+
+    /// Spawns the pager given by `$PAGER`, falling back to `less`, which is passed `-R` to
+    /// preserve colors.
+    fn spawn_pager() -> Option<Child> {
+        let pager = std::env::var("PAGER").ok().filter(|p| !p.trim().is_empty()).unwrap_or("less".to_string());
+        let mut args = pager.split_whitespace();
+        let program = args.next()?;
+        let mut cmd = Command::new(program);
+        cmd.args(args);
+        if program == "less" || program.ends_with("/less") {
+            cmd.arg("-R");
+        }
+        cmd.stdin(Stdio::piped()).spawn().ok()
+    }
+
+    /// Sends buffered output to the pager, or to stdout if the pager can't be started.
+    fn start_pager(&mut self) {
+        let Sink::Buffer(buf) = std::mem::replace(&mut self.sink, Sink::Stdout(std::io::stdout())) else {
+            return;
+        };
+        if let Some(child) = Self::spawn_pager() {
+            self.sink = Sink::Pager(child);
+        }
+        self.write_raw(&buf);
+    }
+
+    fn write_raw(&mut self, buf: &[u8]) {
+        let result = match &mut self.sink {
+            Sink::Stdout(stdout) => stdout.write_all(buf),
+            Sink::Buffer(buffer) => {
+                buffer.extend_from_slice(buf);
+                Ok(())
+            }
+            Sink::Pager(child) => child.stdin.as_mut().unwrap().write_all(buf),
+        };
+        if result.is_err() {
+            self.closed = true;
+        }
+    }
+
+    /// Flushes any buffered output and waits for the pager to exit.
+    pub fn finish(&mut self) {
+        match std::mem::replace(&mut self.sink, Sink::Stdout(std::io::stdout())) {
+            Sink::Buffer(buf) => {
+                self.write_raw(&buf);
+                let _ = self.flush();
+            }
+            Sink::Pager(mut child) => {
+                drop(child.stdin.take());
+                let _ = child.wait();
+            }
+            Sink::Stdout(mut stdout) => {
+                let _ = stdout.flush();
+            }
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.closed {
+            return Ok(buf.len());
+        }
+        self.write_raw(buf);
+        if let Sink::Buffer(_) = self.sink {
+            self.lines += buf.iter().filter(|&&b| b == b'\n').count();
+            if self.lines > self.max_lines {
+                self.start_pager();
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.sink {
+            Sink::Stdout(stdout) => stdout.flush(),
+            Sink::Pager(child) => child.stdin.as_mut().unwrap().flush(),
+            Sink::Buffer(_) => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use std::fmt;
+use std::time::SystemTime;
+
+use object_store::aws::AmazonS3Builder;
+use object_store::path::Path;
+use object_store::ObjectStore;
+
+/// Scheme of the URLs listed from S3.
+pub const SCHEME: &str = "s3://";
+
+/// An `s3://bucket/prefix` URL split into its bucket and key prefix.
+#[derive(Debug, PartialEq)]
+pub struct Url {
+    pub bucket: String,
+    /// Key prefix without leading or trailing `/`, empty for the whole bucket.
+    pub prefix: Path,
+}
+
+impl Url {
+    pub fn parse(url: &str) -> Result<Url, String> {
+        let rest = url.strip_prefix(SCHEME).ok_or_else(|| format!("'{}' is not an {} URL", url, SCHEME))?;
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            return Err(format!("'{}' has no bucket", url));
+        }
+        let prefix = Path::parse(prefix).map_err(|err| format!("invalid key prefix in '{}': {}", url, err))?;
+        Ok(Url { bucket: bucket.to_string(), prefix })
+    }
+
+    /// URL of the object or prefix `key` in this bucket.
+    pub fn of(&self, key: &Path) -> String {
+        if key.as_ref().is_empty() {
+            format!("{}{}", SCHEME, self.bucket)
+        } else {
+            format!("{}{}/{}", SCHEME, self.bucket, key)
+        }
+    }
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.of(&self.prefix))
+    }
+}
+
+/// An object or common prefix found under an S3 URL.
+#[derive(Debug, PartialEq)]
+pub struct Object {
+    /// Key of the object, or of the prefix without its trailing `/`.
+    pub key: Path,
+    /// Number of `/` separated levels below the listed prefix.
+    pub depth: usize,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// Lists the objects up to `depth` levels below `url`, starting with the prefix itself at depth
+/// 0. Credentials and region are read from the `AWS_*` environment variables.
+///
+/// Prefixes are listed like directories, including those between the URL and deeper objects.
+pub fn list(url: &Url, depth: usize) -> Result<Vec<Object>, String> {
+    let store = AmazonS3Builder::from_env()
+        .with_bucket_name(&url.bucket)
+        .build()
+        .map_err(|err| format!("cannot access '{}': {}", url, err))?;
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|err| format!("cannot start the runtime for '{}': {}", url, err))?;
+    runtime.block_on(list_store(&store, &url.prefix, depth)).map_err(|err| format!("cannot list '{}': {}", url, err))
+}
+
+/// Lists `store` below `prefix` one level at a time, so nothing deeper than `depth` is requested.
+async fn list_store(store: &dyn ObjectStore, prefix: &Path, depth: usize) -> object_store::Result<Vec<Object>> {
+    let mut objects = vec![Object { key: prefix.clone(), depth: 0, is_dir: true, size: 0, modified: None }];
+    let mut dirs = vec![prefix.clone()];
+    for level in 1..=depth {
+        let mut subdirs = Vec::new();
+        for dir in &dirs {
+            let listing = store.list_with_delimiter(Some(dir)).await?;
+            // Keys ending in `/` are placeholders for empty prefixes made by the S3 console, and
+            // are listed under the prefix they stand for.
+            objects.extend(listing.objects.into_iter().filter(|meta| meta.location != *dir).map(|meta| Object {
+                key: meta.location,
+                depth: level,
+                is_dir: false,
+                size: meta.size,
+                modified: Some(SystemTime::from(meta.last_modified)),
+            }));
+            for key in listing.common_prefixes {
+                objects.push(Object { key: key.clone(), depth: level, is_dir: true, size: 0, modified: None });
+                subdirs.push(key);
+            }
+        }
+        dirs = subdirs;
+    }
+    Ok(objects)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object_store::memory::InMemory;
+    use object_store::PutPayload;
+
+    fn url(bucket: &str, prefix: &str) -> Url {
+        Url { bucket: bucket.to_string(), prefix: Path::from(prefix) }
+    }
+
+    /// Keys and depths listed from a store holding `keys`, sorted by key.
+    fn listed(keys: &[&str], prefix: &str, depth: usize) -> Vec<(String, usize, bool)> {
+        let store = InMemory::new();
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        runtime.block_on(async {
+            for key in keys {
+                store.put(&Path::from(*key), PutPayload::from_static(b"data")).await.unwrap();
+            }
+            let mut objects = list_store(&store, &Path::from(prefix), depth).await.unwrap();
+            objects.sort_by(|a, b| a.key.cmp(&b.key));
+            objects.into_iter().map(|obj| (obj.key.to_string(), obj.depth, obj.is_dir)).collect()
+        })
+    }
+
+    #[test]
+    fn parse_urls() {
+        assert_eq!(Url::parse("s3://bucket"), Ok(url("bucket", "")));
+        assert_eq!(Url::parse("s3://bucket/"), Ok(url("bucket", "")));
+        assert_eq!(Url::parse("s3://bucket/shots/render/"), Ok(url("bucket", "shots/render")));
+        assert!(Url::parse("s3://").is_err());
+        assert!(Url::parse("s3:///shots").is_err());
+        assert!(Url::parse("s3://bucket/shots//render").is_err());
+        assert!(Url::parse("gs://bucket").is_err());
+
+        let shots = url("bucket", "shots");
+        assert_eq!(shots.to_string(), "s3://bucket/shots");
+        assert_eq!(shots.of(&Path::from("shots/a.0001.exr")), "s3://bucket/shots/a.0001.exr");
+        assert_eq!(url("bucket", "").to_string(), "s3://bucket");
+    }
+
+    #[test]
+    fn list_prefix() {
+        let keys = ["shots/a.0001.exr", "shots/a.0002.exr", "shots/sub/b.txt", "shotsx/c.txt", "top.txt"];
+        // Only whole `/` separated levels of the prefix match, so `shotsx` is left out.
+        assert_eq!(
+            listed(&keys, "shots", 1),
+            [
+                ("shots".to_string(), 0, true),
+                ("shots/a.0001.exr".to_string(), 1, false),
+                ("shots/a.0002.exr".to_string(), 1, false),
+                ("shots/sub".to_string(), 1, true),
+            ]
+        );
+        let root = listed(&keys, "", 1);
+        assert_eq!(root.iter().map(|(key, ..)| key.as_str()).collect::<Vec<_>>(), ["", "shots", "shotsx", "top.txt"]);
+        assert!(listed(&keys, "missing", 2).iter().all(|(_, depth, _)| *depth == 0));
+    }
+
+    #[test]
+    fn list_depth() {
+        let keys = ["shots/a.0001.exr", "shots/sub/b.txt", "shots/sub/deep/c.txt", "shots/sub/deep/er/d.txt"];
+        let at = |depth| listed(&keys, "shots", depth).into_iter().map(|(key, depth, _)| (key, depth)).collect::<Vec<_>>();
+        assert_eq!(at(0), [("shots".to_string(), 0)]);
+        assert_eq!(
+            at(2),
+            [
+                ("shots".to_string(), 0),
+                ("shots/a.0001.exr".to_string(), 1),
+                ("shots/sub".to_string(), 1),
+                ("shots/sub/b.txt".to_string(), 2),
+                ("shots/sub/deep".to_string(), 2),
+            ]
+        );
+        // Prefixes only holding deeper objects are still listed like directories.
+        assert_eq!(at(3).last(), Some(&("shots/sub/deep/er".to_string(), 3)));
+        assert_eq!(at(3).len(), 7);
+    }
+}